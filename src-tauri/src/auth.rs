@@ -0,0 +1,102 @@
+//! Access control for clients of the local server
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::config::ServerConfig;
+use crate::runtime::Tokens;
+
+/// What an authenticated client may do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    /// Observe state only
+    Read,
+    /// Observe and drive the mascot
+    Control,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthError {
+    /// Missing or unknown token
+    Unauthorized,
+    /// Origin not in `server.allowed_origins`
+    ForbiddenOrigin,
+}
+
+impl AuthError {
+    pub fn status(self) -> u16 {
+        match self {
+            AuthError::Unauthorized => 401,
+            AuthError::ForbiddenOrigin => 403,
+        }
+    }
+
+    pub fn message(self) -> &'static str {
+        match self {
+            AuthError::Unauthorized => "missing or invalid token",
+            AuthError::ForbiddenOrigin => "origin not allowed",
+        }
+    }
+}
+
+pub struct AuthPolicy {
+    tokens: Tokens,
+    allowed_origins: Vec<String>,
+    anonymous_read: bool,
+}
+
+impl AuthPolicy {
+    pub fn new(config: &ServerConfig, tokens: Tokens) -> Self {
+        Self {
+            tokens,
+            allowed_origins: config.allowed_origins.clone(),
+            anonymous_read: config.anonymous_read,
+        }
+    }
+
+    pub fn origin_allowed(&self, origin: &str) -> bool {
+        self.allowed_origins
+            .iter()
+            .any(|o| o == "*" || o.eq_ignore_ascii_case(origin))
+    }
+
+    pub fn authorize(&self, origin: Option<&str>, token: Option<&str>) -> Result<Scope, AuthError> {
+        if origin.is_some_and(|o| !self.origin_allowed(o)) {
+            return Err(AuthError::ForbiddenOrigin);
+        }
+
+        match token {
+            Some(t) if constant_time_eq(t, &self.tokens.control) => Ok(Scope::Control),
+            Some(t) if constant_time_eq(t, &self.tokens.read) => Ok(Scope::Read),
+            None if self.anonymous_read => Ok(Scope::Read),
+            _ => Err(AuthError::Unauthorized),
+        }
+    }
+}
+
+/// Find the token in a request: `?token=` query parameter, `Authorization:
+/// Bearer` header, or `X-Claudy-Token` header
+pub fn request_token<'a>(
+    query: Option<&'a str>,
+    header: impl Fn(&str) -> Option<&'a str>,
+) -> Option<&'a str> {
+    query
+        .and_then(|q| {
+            q.split('&')
+                .find_map(|pair| pair.strip_prefix("token="))
+        })
+        .or_else(|| {
+            header("authorization").and_then(|v| v.strip_prefix("Bearer ").map(str::trim))
+        })
+        .or_else(|| header("x-claudy-token").map(str::trim))
+        .filter(|t| !t.is_empty())
+}
+
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0u8, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
@@ -0,0 +1,170 @@
+//! Hiding the mascot on its own: after `behavior.sleepy_timeout` without
+//! activity, and while a full-screen window covers its monitor. Showing or
+//! hiding it by hand wins over both.
+
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::WebviewWindow;
+use tokio::sync::watch;
+
+use crate::config::{BehaviorConfig, Config};
+use crate::focus;
+use crate::pipeline::{Sink, StateUpdate};
+use crate::watcher::ClaudeEvent;
+
+/// How often the timeout and the full-screen window are checked
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+struct Visibility {
+    /// Hidden from the tray, or never shown yet
+    user_hidden: bool,
+    idle_hidden: bool,
+    last_activity: Instant,
+    /// A full-screen window is focused on the mascot's monitor
+    fullscreen: bool,
+    /// Shown by hand over the current full-screen window
+    fullscreen_overridden: bool,
+}
+
+impl Visibility {
+    fn visible(&self) -> bool {
+        let covered = self.fullscreen && !self.fullscreen_overridden;
+        !(self.user_hidden || self.idle_hidden || covered)
+    }
+
+    fn wake(&mut self) {
+        self.idle_hidden = false;
+        self.last_activity = Instant::now();
+    }
+}
+
+/// Decides whether the mascot window is shown. Also a pipeline sink, since
+/// session activity wakes it.
+#[derive(Clone)]
+pub struct AutoHide {
+    visibility: Arc<Mutex<Visibility>>,
+    window: Arc<Mutex<Option<WebviewWindow>>>,
+}
+
+impl Default for AutoHide {
+    fn default() -> Self {
+        Self {
+            visibility: Arc::new(Mutex::new(Visibility {
+                user_hidden: true,
+                idle_hidden: false,
+                last_activity: Instant::now(),
+                fullscreen: false,
+                fullscreen_overridden: false,
+            })),
+            window: Arc::default(),
+        }
+    }
+}
+
+impl AutoHide {
+    /// Take over the window's visibility and start checking, with
+    /// `behavior` from the config as it's reloaded
+    pub fn spawn(&self, window: WebviewWindow, config: watch::Receiver<Arc<Config>>) {
+        self.visibility.lock().unwrap().user_hidden = !window.is_visible().unwrap_or(false);
+        *self.window.lock().unwrap() = Some(window.clone());
+
+        let autohide = self.clone();
+        thread::spawn(move || loop {
+            thread::sleep(CHECK_INTERVAL);
+            let behavior = config.borrow().behavior.clone();
+            autohide.check(&window, &behavior);
+        });
+    }
+
+    /// Show the window, overriding auto-hiding until it next kicks in
+    pub fn show(&self) {
+        {
+            let mut v = self.visibility.lock().unwrap();
+            v.user_hidden = false;
+            v.fullscreen_overridden = v.fullscreen;
+            v.wake();
+        }
+        self.apply();
+    }
+
+    /// The tray's Show/Hide
+    pub fn toggle(&self) {
+        let visible = self.visibility.lock().unwrap().visible();
+        if visible {
+            self.visibility.lock().unwrap().user_hidden = true;
+            self.apply();
+        } else {
+            self.show();
+        }
+    }
+
+    fn check(&self, window: &WebviewWindow, behavior: &BehaviorConfig) {
+        {
+            let mut v = self.visibility.lock().unwrap();
+            v.idle_hidden = behavior.auto_hide_idle
+                && (v.idle_hidden
+                    || v.last_activity.elapsed() >= Duration::from_secs(behavior.sleepy_timeout.into()));
+        }
+
+        // The probe runs a subprocess, so only when asked for and with the
+        // lock released
+        let fullscreen = behavior.hide_on_fullscreen && fullscreen_on_monitor(window);
+        {
+            let mut v = self.visibility.lock().unwrap();
+            if v.fullscreen != fullscreen {
+                v.fullscreen = fullscreen;
+                v.fullscreen_overridden = false;
+            }
+        }
+
+        self.apply();
+    }
+
+    fn apply(&self) {
+        let visible = self.visibility.lock().unwrap().visible();
+        let Some(window) = self.window.lock().unwrap().clone() else {
+            return;
+        };
+        if window.is_visible().unwrap_or(false) != visible {
+            let _ = if visible { window.show() } else { window.hide() };
+        }
+    }
+}
+
+impl Sink for AutoHide {
+    fn handle(&self, update: &StateUpdate) {
+        let mut v = self.visibility.lock().unwrap();
+        match update.event {
+            ClaudeEvent::SessionStart { .. } | ClaudeEvent::UserMessage { .. } => {
+                let was_hidden = v.idle_hidden;
+                v.wake();
+                drop(v);
+                if was_hidden {
+                    // Sinks must not block; showing goes through the event loop
+                    let autohide = self.clone();
+                    thread::spawn(move || autohide.apply());
+                }
+            }
+            // Other activity only pushes the timeout back while still shown
+            _ if !v.idle_hidden => v.last_activity = Instant::now(),
+            _ => {}
+        }
+    }
+}
+
+/// Whether the focused window fills the whole monitor the mascot is on
+fn fullscreen_on_monitor(window: &WebviewWindow) -> bool {
+    let Some((x, y, width, height)) = focus::active_window_geometry() else {
+        return false;
+    };
+    let Ok(Some(monitor)) = window.current_monitor() else {
+        return false;
+    };
+    let position = monitor.position();
+    let size = monitor.size();
+    x <= position.x as f64
+        && y <= position.y as f64
+        && x + width >= position.x as f64 + size.width as f64
+        && y + height >= position.y as f64 + size.height as f64
+}
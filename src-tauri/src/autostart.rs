@@ -0,0 +1,159 @@
+//! Starting Claudy with the session, either the app from an XDG autostart
+//! entry or the daemon from a systemd user unit. `behavior.auto_start`
+//! mirrors whether one is installed.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::config;
+
+const DESKTOP_FILE: &str = "claudy.desktop";
+const UNIT_FILE: &str = "claudy.service";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    /// `~/.config/autostart/claudy.desktop`, launching the app
+    Desktop,
+    /// `~/.config/systemd/user/claudy.service`, running `claudy-cli daemon`
+    Systemd,
+}
+
+impl Method {
+    fn entry_path(self) -> Option<PathBuf> {
+        let dir = dirs::config_dir()?;
+        Some(match self {
+            Method::Desktop => dir.join("autostart").join(DESKTOP_FILE),
+            Method::Systemd => dir.join("systemd").join("user").join(UNIT_FILE),
+        })
+    }
+}
+
+/// The installed entry, if any
+pub fn status() -> Option<(Method, PathBuf)> {
+    [Method::Desktop, Method::Systemd]
+        .into_iter()
+        .find_map(|method| method.entry_path().filter(|p| p.exists()).map(|p| (method, p)))
+}
+
+/// Install an entry starting `exe`: the app for `Desktop`, `claudy-cli` for
+/// `Systemd`. Replaces an entry of the other kind.
+pub fn enable(method: Method, exe: &Path) -> io::Result<PathBuf> {
+    if !cfg!(target_os = "linux") {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "autostart is only supported on Linux",
+        ));
+    }
+
+    disable()?;
+    let path = method
+        .entry_path()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    match method {
+        Method::Desktop => fs::write(&path, desktop_entry(exe))?,
+        Method::Systemd => {
+            fs::write(&path, systemd_unit(exe))?;
+            systemctl(&["daemon-reload"])?;
+            systemctl(&["enable", UNIT_FILE])?;
+        }
+    }
+    Ok(path)
+}
+
+/// Remove every installed entry, returning their paths. A running daemon
+/// keeps running.
+pub fn disable() -> io::Result<Vec<PathBuf>> {
+    let mut removed = Vec::new();
+    for method in [Method::Desktop, Method::Systemd] {
+        let Some(path) = method.entry_path().filter(|p| p.exists()) else {
+            continue;
+        };
+        if method == Method::Systemd {
+            // Drops the default.target link; fine to fail without systemd
+            let _ = systemctl(&["disable", UNIT_FILE]);
+        }
+        fs::remove_file(&path)?;
+        if method == Method::Systemd {
+            let _ = systemctl(&["daemon-reload"]);
+        }
+        removed.push(path);
+    }
+    Ok(removed)
+}
+
+/// Update `behavior.auto_start` to whether an entry is installed, e.g. after
+/// one was added or removed by hand. Returns the flag.
+pub fn sync_config() -> Result<bool, Box<dyn std::error::Error>> {
+    let enabled = status().is_some();
+    let mut cfg = config::load_config();
+    if cfg.behavior.auto_start != enabled {
+        cfg.behavior.auto_start = enabled;
+        config::save_config(&cfg)?;
+    }
+    Ok(enabled)
+}
+
+fn desktop_entry(exe: &Path) -> String {
+    format!(
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Name=Claudy\n\
+         Comment=Your Claude Code companion\n\
+         Exec={}\n\
+         Terminal=false\n\
+         X-GNOME-Autostart-enabled=true\n",
+        quote(exe)
+    )
+}
+
+fn systemd_unit(exe: &Path) -> String {
+    format!(
+        "[Unit]\n\
+         Description=Claudy, your Claude Code companion\n\
+         \n\
+         [Service]\n\
+         ExecStart={} daemon\n\
+         Restart=on-failure\n\
+         \n\
+         [Install]\n\
+         WantedBy=default.target\n",
+        quote(exe)
+    )
+}
+
+/// Quote a path for an `Exec=` or `ExecStart=` line; both accept double
+/// quotes with backslash escapes
+fn quote(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    if !path.contains(|c: char| c.is_whitespace() || "\"'\\$`".contains(c)) {
+        return path.into_owned();
+    }
+    let mut quoted = String::from("\"");
+    for c in path.chars() {
+        if matches!(c, '"' | '\\' | '$' | '`') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn systemctl(args: &[&str]) -> io::Result<()> {
+    let output = Command::new("systemctl").arg("--user").args(args).output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "systemctl --user {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )))
+    }
+}
@@ -0,0 +1,108 @@
+//! Everything Claudy runs besides the window: shared state, event pipeline,
+//! speech queue and servers. Used by the app and by the headless daemon.
+
+use std::sync::{Arc, Mutex};
+use tokio::runtime::Handle;
+
+use crate::commands::ServerContext;
+use crate::config::Config;
+use crate::history::{HistorySink, SharedHistory};
+use crate::instance;
+use crate::pipeline::{Pipeline, PipelineHandle, Sink};
+use crate::presence::ClientRegistry;
+use crate::quiet::Quiet;
+use crate::runtime;
+use crate::sinks;
+use crate::speech::Speaker;
+use crate::state::{ClaudyState, SharedState};
+use crate::websocket::{self, BroadcastSink};
+
+pub struct Backend {
+    pub state: SharedState,
+    pub context: ServerContext,
+    pipeline: Pipeline,
+}
+
+impl Backend {
+    pub fn new(config: &Config, runtime: &Handle) -> Self {
+        let state: SharedState = Arc::new(Mutex::new(ClaudyState::new()));
+        let broadcaster = websocket::new_broadcaster(config.server.queue_size);
+        let history = SharedHistory::default();
+        let pipeline = Pipeline::new(state.clone(), config)
+            .with_sink(HistorySink::new(history.clone()))
+            .with_sink(BroadcastSink::new(broadcaster.clone()));
+        let quiet = Quiet::spawn(runtime, pipeline.controller().subscribe_config());
+        let speaker = Speaker::spawn(
+            runtime,
+            state.clone(),
+            broadcaster.clone(),
+            quiet.clone(),
+            pipeline.controller().subscribe_config(),
+        );
+
+        let context = ServerContext {
+            state: state.clone(),
+            broadcaster,
+            history,
+            speaker,
+            clients: ClientRegistry::default(),
+            pipeline: pipeline.controller(),
+            app: instance::new_app_requests(),
+            quiet,
+        };
+
+        Self {
+            state,
+            context,
+            pipeline,
+        }
+    }
+
+    pub fn with_sink(mut self, sink: impl Sink + 'static) -> Self {
+        self.pipeline = self.pipeline.with_sink(sink);
+        self
+    }
+
+    /// Start the WebSocket/HTTP server and the control socket, as enabled in
+    /// `[server]`. Failures to listen are reported through `on_error`.
+    pub fn start_servers(&self, config: &Config, runtime: &Handle, on_error: impl Fn(String) + Send + 'static) {
+        if config.server.enabled {
+            let context = self.context.clone();
+            let server_config = config.server.clone();
+            runtime.spawn(async move {
+                if let Err(e) = websocket::start_server(context, &server_config).await {
+                    eprintln!(
+                        "[Claudy WS] Failed to start server on {}:{}: {}",
+                        server_config.bind, server_config.port, e
+                    );
+                    on_error(format!("Could not listen on port {}: {}", server_config.port, e));
+                }
+            });
+        }
+
+        // Control socket for local tooling
+        #[cfg(unix)]
+        if config.server.socket {
+            let context = self.context.clone();
+            runtime.spawn(async move {
+                if let Err(e) = crate::ipc::start_socket(context).await {
+                    eprintln!("[Claudy IPC] Failed to start control socket: {}", e);
+                }
+            });
+        }
+    }
+
+    /// Start the event pipeline for registered projects, with the sinks from
+    /// `[[sinks]]` after the built-in ones
+    pub fn spawn_pipeline(self, runtime: &Handle) -> Result<PipelineHandle, notify::Error> {
+        let configured = sinks::ConfiguredSinks::spawn(self.pipeline.controller().subscribe_config(), runtime);
+        self.pipeline.with_sink(configured).spawn(runtime)
+    }
+}
+
+/// Remove the files advertising this process to local tools
+pub fn cleanup() {
+    runtime::remove_server_info();
+    #[cfg(unix)]
+    crate::ipc::remove_socket();
+}
@@ -1,7 +1,28 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use std::env;
+use std::process;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tokio::sync::broadcast;
 
-use claudy_lib::config;
+use claudy_lib::protocol::{self, Command, ServerBody, Subscription};
+use claudy_lib::backend::{self, Backend};
+use claudy_lib::config::WindowMode;
+use claudy_lib::instance::{self, AppRequest, InstanceLock, LockError};
+use claudy_lib::pipeline::{Pipeline, Sink, StateUpdate};
+use claudy_lib::presence::ClientInfo;
+use claudy_lib::quiet::QuietStatus;
+use claudy_lib::speech::{Priority, SayRequest};
+use claudy_lib::state::{ClaudyState, SharedState};
+use claudy_lib::statusbar::{BarFormat, BarStatus};
+use claudy_lib::{autostart, client, config, runtime};
+
+/// The app's executable, installed next to `claudy-cli`
+#[cfg(windows)]
+const APP_BINARY: &str = "claudy.exe";
+#[cfg(not(windows))]
+const APP_BINARY: &str = "claudy";
 
 #[derive(Parser)]
 #[command(name = "claudy")]
@@ -13,6 +34,17 @@ struct Cli {
 
 #[derive(Subcommand)]
 enum Commands {
+    /// Run the watcher and servers without a window (the default)
+    Daemon,
+    /// Stop the running app or daemon
+    Stop,
+    /// Stop the running app or daemon and start it again
+    Restart,
+    /// Start Claudy with your session
+    Autostart {
+        #[command(subcommand)]
+        action: AutostartAction,
+    },
     /// Register current directory for watching
     Register,
     /// Unregister current directory
@@ -23,18 +55,204 @@ enum Commands {
     Status,
     /// Show config file location
     Config,
+    /// Print the token clients need to connect to the server
+    Token {
+        /// Print the read-only token instead of the control token
+        #[arg(long)]
+        read: bool,
+    },
+    /// Make Claudy say something
+    Say {
+        text: String,
+        /// Seconds to show the bubble for (defaults to notifications.bubble_duration)
+        #[arg(long)]
+        duration: Option<u32>,
+        /// high interrupts the current bubble, low waits behind the rest
+        #[arg(long, value_enum, default_value_t = PriorityArg::Normal)]
+        priority: PriorityArg,
+        /// State to switch to while talking, e.g. happy
+        #[arg(long)]
+        mood: Option<String>,
+    },
+    /// Make a project the focused one
+    Focus {
+        /// Project path or slug (defaults to the current directory)
+        project: Option<String>,
+    },
+    /// Stop reacting to Claude Code activity
+    Pause,
+    /// Resume after pause
+    Resume,
+    /// Reload the config file
+    Reload,
+    /// Show, or turn on or off, Do-Not-Disturb
+    Dnd {
+        #[arg(value_enum)]
+        state: Option<Toggle>,
+        /// Turn it off again after a while, e.g. 30m, 1h or 1h30m
+        #[arg(long = "for", value_parser = parse_duration)]
+        duration: Option<Duration>,
+    },
+    /// Show or change how the mascot window behaves
+    Window {
+        #[arg(long, value_enum)]
+        always_on_top: Option<Toggle>,
+        /// Let clicks pass through to the windows underneath
+        #[arg(long, value_enum)]
+        click_through: Option<Toggle>,
+        #[arg(long, value_enum)]
+        skip_taskbar: Option<Toggle>,
+        /// Show on every workspace
+        #[arg(long, value_enum)]
+        all_workspaces: Option<Toggle>,
+    },
+    /// Print a status line whenever the state changes, for status bars
+    Watch {
+        #[arg(long, value_enum, default_value_t = FormatArg::Plain)]
+        format: FormatArg,
+        /// Print the current status once and exit
+        #[arg(long)]
+        once: bool,
+    },
+    /// List clients connected to the server
+    Clients {
+        #[command(subcommand)]
+        action: Option<ClientsAction>,
+    },
+    /// Print the JSON Schema of the WebSocket protocol
+    Schema {
+        #[arg(value_enum, default_value_t = SchemaKind::Client)]
+        kind: SchemaKind,
+    },
+}
+
+#[derive(Subcommand)]
+enum AutostartAction {
+    /// Start the app on login, or the daemon with --daemon
+    Enable {
+        /// Install a systemd user unit running `claudy-cli daemon`
+        #[arg(long)]
+        daemon: bool,
+    },
+    Disable,
+    Status,
+}
+
+#[derive(Subcommand)]
+enum ClientsAction {
+    /// Disconnect a client
+    Kick { id: u64 },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum PriorityArg {
+    Low,
+    Normal,
+    High,
+}
+
+impl From<PriorityArg> for Priority {
+    fn from(priority: PriorityArg) -> Self {
+        match priority {
+            PriorityArg::Low => Priority::Low,
+            PriorityArg::Normal => Priority::Normal,
+            PriorityArg::High => Priority::High,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Toggle {
+    On,
+    Off,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum FormatArg {
+    Waybar,
+    Polybar,
+    I3blocks,
+    Tmux,
+    Plain,
+    Json,
+}
+
+impl From<FormatArg> for BarFormat {
+    fn from(format: FormatArg) -> Self {
+        match format {
+            FormatArg::Waybar => BarFormat::Waybar,
+            FormatArg::Polybar => BarFormat::Polybar,
+            FormatArg::I3blocks => BarFormat::I3blocks,
+            FormatArg::Tmux => BarFormat::Tmux,
+            FormatArg::Plain => BarFormat::Plain,
+            FormatArg::Json => BarFormat::Json,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum SchemaKind {
+    /// Messages sent by clients
+    Client,
+    /// Messages sent by the server
+    Server,
 }
 
 fn main() {
     let cli = Cli::parse();
 
     match cli.command {
+        Some(Commands::Daemon) | None => start_daemon(),
+        Some(Commands::Stop) => stop_instance(),
+        Some(Commands::Restart) => restart_instance(),
+        Some(Commands::Autostart { action }) => autostart(action),
         Some(Commands::Register) => register_project(),
         Some(Commands::Unregister) => unregister_project(),
         Some(Commands::List) => list_projects(),
         Some(Commands::Status) => show_status(),
         Some(Commands::Config) => show_config(),
-        None => start_daemon(),
+        Some(Commands::Token { read }) => show_token(read),
+        Some(Commands::Say {
+            text,
+            duration,
+            priority,
+            mood,
+        }) => say(SayRequest {
+            text,
+            duration,
+            priority: priority.into(),
+            mood,
+        }),
+        Some(Commands::Focus { project }) => focus_project(project),
+        Some(Commands::Pause) => set_paused(true),
+        Some(Commands::Resume) => set_paused(false),
+        Some(Commands::Reload) => reload_config(),
+        Some(Commands::Dnd { state, duration }) => dnd(state, duration),
+        Some(Commands::Window {
+            always_on_top,
+            click_through,
+            skip_taskbar,
+            all_workspaces,
+        }) => {
+            let changes = [
+                (WindowMode::AlwaysOnTop, always_on_top),
+                (WindowMode::ClickThrough, click_through),
+                (WindowMode::SkipTaskbar, skip_taskbar),
+                (WindowMode::AllWorkspaces, all_workspaces),
+            ];
+            set_window_modes(
+                changes
+                    .into_iter()
+                    .filter_map(|(mode, toggle)| Some((mode, matches!(toggle?, Toggle::On))))
+                    .collect(),
+            )
+        }
+        Some(Commands::Watch { format, once }) => watch(format.into(), once),
+        Some(Commands::Clients { action: None }) => list_clients(),
+        Some(Commands::Clients {
+            action: Some(ClientsAction::Kick { id }),
+        }) => kick_client(id),
+        Some(Commands::Schema { kind }) => show_schema(kind),
     }
 }
 
@@ -42,16 +260,22 @@ fn register_project() {
     let current_dir = env::current_dir().expect("Could not get current directory");
     let path = current_dir.to_string_lossy().to_string();
 
-    let mut cfg = config::load_config();
-
-    if cfg.projects.registered.contains(&path) {
-        println!("Project already registered: {}", path);
+    // Let the running instance start watching right away
+    if instance::is_running() {
+        let result = send(Command::RegisterProject { path: path.clone() }, "register project");
+        if result["registered"].as_bool().unwrap_or(false) {
+            println!("Registered: {}", path);
+        } else {
+            println!("Project already registered: {}", path);
+        }
         return;
     }
 
-    cfg.projects.registered.push(path.clone());
-    config::save_config(&cfg).expect("Failed to save config");
-    println!("Registered: {}", path);
+    if config::register_project(&path).expect("Failed to save config") {
+        println!("Registered: {}", path);
+    } else {
+        println!("Project already registered: {}", path);
+    }
 }
 
 fn unregister_project() {
@@ -85,8 +309,156 @@ fn list_projects() {
 }
 
 fn show_status() {
-    println!("Claudy status: Not yet implemented");
-    // TODO: Check if daemon is running, show active sessions
+    let status = match client::request(Command::Status) {
+        Ok(status) => status,
+        Err(_) => {
+            println!("Claudy is not running.");
+            return;
+        }
+    };
+
+    let paused = status["paused"].as_bool().unwrap_or(false);
+    println!(
+        "Claudy is running (pid {}){}",
+        status["pid"],
+        if paused { ", paused" } else { "" }
+    );
+    println!("State: {}", status["state"].as_str().unwrap_or("unknown"));
+    if status["quiet"]["quiet"].as_bool().unwrap_or(false) {
+        println!("Quiet: notifications and bubbles are suppressed");
+    }
+
+    if let Ok(info) = serde_json::from_value::<runtime::ServerInfo>(status["server"].clone()) {
+        println!("Server: {} ({} clients)", info.ws_url(), status["clients"]);
+    }
+    println!("Socket: {}", runtime::socket_path().display());
+
+    let focused = status["focused_project"].as_str();
+    let projects = status["active_projects"].as_array().cloned().unwrap_or_default();
+    if projects.is_empty() {
+        println!("No active sessions.");
+    }
+    for project in projects.iter().filter_map(|p| p.as_str()) {
+        let marker = if Some(project) == focused { "*" } else { " " };
+        println!("  {} {}", marker, project);
+    }
+}
+
+/// Run a command against the running app, exiting on failure
+fn send(command: Command, action: &str) -> serde_json::Value {
+    match client::request(command) {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("Failed to {}: {}", action, e);
+            process::exit(1);
+        }
+    }
+}
+
+fn focus_project(project: Option<String>) {
+    let project = project.unwrap_or_else(|| {
+        let current_dir = env::current_dir().expect("Could not get current directory");
+        current_dir.to_string_lossy().to_string()
+    });
+    send(Command::FocusProject { project: project.clone() }, "focus project");
+    println!("Focused: {}", project);
+}
+
+fn set_paused(paused: bool) {
+    send(Command::Pause { paused }, if paused { "pause" } else { "resume" });
+    println!("{}", if paused { "Paused." } else { "Resumed." });
+}
+
+fn reload_config() {
+    send(Command::Reload, "reload config");
+    println!("Config reloaded.");
+}
+
+fn dnd(state: Option<Toggle>, duration: Option<Duration>) {
+    let status = match state {
+        Some(toggle) => send(
+            Command::SetDnd {
+                enabled: matches!(toggle, Toggle::On),
+                duration: duration.map(|d| d.as_secs()),
+            },
+            "change Do-Not-Disturb",
+        ),
+        None => send(Command::Status, "get the status")["quiet"].take(),
+    };
+    let status: QuietStatus = serde_json::from_value(status).unwrap_or_default();
+
+    match (status.dnd, status.dnd_until) {
+        (true, Some(until)) => {
+            let left = until.saturating_sub(claudy_lib::history::now_millis());
+            println!("Do-Not-Disturb is on for {} more minutes.", left.div_ceil(60_000));
+        }
+        (true, None) => println!("Do-Not-Disturb is on."),
+        (false, _) if status.quiet => println!("Do-Not-Disturb is off, but it's quiet hours."),
+        (false, _) => println!("Do-Not-Disturb is off."),
+    }
+}
+
+/// `90s`, `30m`, `1h` or `1h30m`; a bare number is minutes
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid duration {:?}, expected e.g. 30m or 1h", value);
+    let mut seconds: u64 = 0;
+    let mut number = String::new();
+    let mut add = |number: &str, unit: u64| {
+        seconds = number
+            .parse::<u64>()
+            .ok()
+            .and_then(|n| n.checked_mul(unit))
+            .and_then(|n| n.checked_add(seconds))
+            .ok_or_else(invalid)?;
+        Ok::<_, String>(())
+    };
+    for c in value.trim().chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return Err(invalid()),
+        };
+        add(&number, unit)?;
+        number.clear();
+    }
+    if !number.is_empty() {
+        add(&number, 60)?;
+    }
+    if seconds == 0 {
+        return Err(invalid());
+    }
+    Ok(Duration::from_secs(seconds))
+}
+
+fn set_window_modes(changes: Vec<(WindowMode, bool)>) {
+    if instance::is_running() {
+        for (mode, enabled) in changes {
+            send(Command::SetWindowMode { mode, enabled }, "change the window mode");
+        }
+    } else if !changes.is_empty() {
+        // Applied on the next start
+        let mut cfg = config::load_config();
+        for (mode, enabled) in changes {
+            cfg.window.set(mode, enabled);
+        }
+        config::save_config(&cfg).expect("Failed to save config");
+    }
+
+    let modes = config::load_config().window;
+    for mode in WindowMode::ALL {
+        let name = match mode {
+            WindowMode::AlwaysOnTop => "always-on-top",
+            WindowMode::ClickThrough => "click-through",
+            WindowMode::SkipTaskbar => "skip-taskbar",
+            WindowMode::AllWorkspaces => "all-workspaces",
+        };
+        println!("{:<15} {}", name, if modes.get(mode) { "on" } else { "off" });
+    }
 }
 
 fn show_config() {
@@ -94,8 +466,370 @@ fn show_config() {
     println!("Config file: {}", path.display());
 }
 
+fn show_token(read: bool) {
+    match runtime::load_or_create_tokens() {
+        Ok(tokens) => println!("{}", if read { tokens.read } else { tokens.control }),
+        Err(e) => eprintln!("Failed to load tokens: {}", e),
+    }
+}
+
+fn say(request: SayRequest) {
+    match send(Command::Say(request), "say").as_str() {
+        Some("duplicate") => println!("Already saying that."),
+        Some("queued") => println!("Queued."),
+        Some("suppressed") => println!("Not shown: quiet hours or Do-Not-Disturb."),
+        Some("queue_full") => {
+            eprintln!("Not queued: too many bubbles are waiting.");
+            process::exit(1);
+        }
+        _ => {}
+    }
+}
+
+/// Prints status lines, skipping repeats
+struct StatusPrinter {
+    format: BarFormat,
+    once: bool,
+    /// For project names, read once since a watch can run for days
+    registered: Vec<String>,
+    last: Option<String>,
+}
+
+impl StatusPrinter {
+    fn new(format: BarFormat, once: bool) -> Self {
+        Self {
+            format,
+            once,
+            registered: config::load_config().projects.registered,
+            last: None,
+        }
+    }
+
+    fn print(&mut self, state: &ClaudyState) {
+        let line = BarStatus::new(state, &self.registered).render(self.format);
+        if self.last.as_ref() != Some(&line) {
+            println!("{}", line);
+            self.last = Some(line);
+        }
+        if self.once {
+            process::exit(0);
+        }
+    }
+}
+
+/// Pipeline sink handing states to the printing thread
+struct ChannelSink(mpsc::Sender<ClaudyState>);
+
+impl Sink for ChannelSink {
+    fn handle(&self, update: &StateUpdate) {
+        let _ = self.0.send(update.state.clone());
+    }
+}
+
+fn watch(format: BarFormat, once: bool) {
+    let mut printer = StatusPrinter::new(format, once);
+
+    loop {
+        let mut connected = false;
+        let subscription = Subscription::default();
+        let _ = client::watch(subscription, |message| {
+            connected = true;
+            if let ServerBody::State { state } = message.body {
+                printer.print(&state);
+            }
+        });
+
+        if !connected {
+            break;
+        }
+        // The app went away; reconnect, or take over below
+        thread::sleep(Duration::from_secs(2));
+    }
+
+    watch_headless(printer);
+}
+
+/// Follow transcripts in-process when the app isn't running
+fn watch_headless(mut printer: StatusPrinter) {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("Failed to start async runtime");
+
+    let state: SharedState = Arc::new(Mutex::new(ClaudyState::new()));
+    let (tx, rx) = mpsc::channel();
+    let pipeline = Pipeline::new(state.clone(), &claudy_lib::config::load_config()).with_sink(ChannelSink(tx));
+    let _handle = match pipeline.spawn(runtime.handle()) {
+        Ok(handle) => handle,
+        Err(e) => {
+            eprintln!("Failed to watch transcripts: {}", e);
+            process::exit(1);
+        }
+    };
+
+    if printer.once {
+        // Give the initial backfill a moment
+        thread::sleep(Duration::from_millis(500));
+    }
+    let initial = state.lock().unwrap().clone();
+    printer.print(&initial);
+
+    for state in rx {
+        printer.print(&state);
+    }
+}
+
+fn list_clients() {
+    let result = send(Command::ListClients, "list clients");
+    let clients = serde_json::from_value::<Vec<ClientInfo>>(result).unwrap_or_default();
+
+    if clients.is_empty() {
+        println!("No clients connected.");
+        return;
+    }
+
+    let now = claudy_lib::history::now_millis();
+    for c in clients {
+        let label = match (&c.name, &c.role) {
+            (Some(name), Some(role)) => format!("{} ({})", name, role),
+            (Some(name), None) => name.clone(),
+            (None, Some(role)) => format!("({})", role),
+            (None, None) => "-".to_string(),
+        };
+        println!(
+            "{:>4}  {:<22} {:<24} {:<8} {:>6}s  lagged {}",
+            c.id,
+            c.addr,
+            label,
+            format!("{:?}", c.scope).to_lowercase(),
+            now.saturating_sub(c.connected_since) / 1000,
+            c.lagged
+        );
+    }
+}
+
+fn kick_client(id: u64) {
+    send(Command::KickClient { id }, "kick client");
+    println!("Kicked client {}", id);
+}
+
+fn show_schema(kind: SchemaKind) {
+    let schema = match kind {
+        SchemaKind::Client => protocol::client_schema(),
+        SchemaKind::Server => protocol::server_schema(),
+    };
+    match serde_json::to_string_pretty(&schema) {
+        Ok(json) => println!("{}", json),
+        Err(e) => eprintln!("Failed to serialize schema: {}", e),
+    }
+}
+
+fn autostart(action: AutostartAction) {
+    match action {
+        AutostartAction::Enable { daemon } => {
+            let exe = env::current_exe().expect("Could not locate claudy-cli");
+            let (method, exe) = if daemon {
+                (autostart::Method::Systemd, exe)
+            } else {
+                // The app is installed next to the CLI
+                (autostart::Method::Desktop, exe.with_file_name(APP_BINARY))
+            };
+            match autostart::enable(method, &exe) {
+                Ok(path) => println!("Autostart enabled: {}", path.display()),
+                Err(e) => {
+                    eprintln!("Failed to enable autostart: {}", e);
+                    process::exit(1);
+                }
+            }
+        }
+        AutostartAction::Disable => match autostart::disable() {
+            Ok(removed) if removed.is_empty() => println!("Autostart is not enabled."),
+            Ok(removed) => {
+                for path in removed {
+                    println!("Removed: {}", path.display());
+                }
+            }
+            Err(e) => {
+                eprintln!("Failed to disable autostart: {}", e);
+                process::exit(1);
+            }
+        },
+        AutostartAction::Status => match autostart::status() {
+            Some((autostart::Method::Desktop, path)) => println!("Starting the app on login: {}", path.display()),
+            Some((autostart::Method::Systemd, path)) => println!("Starting the daemon on login: {}", path.display()),
+            None => println!("Autostart is not enabled."),
+        },
+    }
+
+    if let Err(e) = autostart::sync_config() {
+        eprintln!("Failed to update behavior.auto_start: {}", e);
+    }
+}
+
+/// How long `stop` waits for the instance to exit
+const STOP_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn stop_instance() {
+    if !instance::is_running() {
+        println!("Claudy is not running.");
+        return;
+    }
+    stop_and_wait();
+    println!("Stopped.");
+}
+
+fn restart_instance() {
+    let Some(running) = instance::running_instance() else {
+        println!("Claudy is not running.");
+        process::exit(1);
+    };
+    stop_and_wait();
+
+    let mut command = process::Command::new(&running.exe);
+    if running.headless {
+        command.arg("daemon");
+    }
+    command
+        .stdin(process::Stdio::null())
+        .stdout(process::Stdio::null())
+        .stderr(process::Stdio::null());
+    // Keep it running after this terminal goes away
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+
+    match command.spawn() {
+        Ok(child) => println!("Restarted Claudy (pid {}).", child.id()),
+        Err(e) => {
+            eprintln!("Failed to start {}: {}", running.exe.display(), e);
+            process::exit(1);
+        }
+    }
+}
+
+/// Ask the running instance to quit and wait until it released its lock
+fn stop_and_wait() {
+    send(Command::Shutdown, "stop Claudy");
+
+    let started = std::time::Instant::now();
+    while instance::is_running() {
+        if started.elapsed() > STOP_TIMEOUT {
+            eprintln!("Claudy did not stop within {} seconds.", STOP_TIMEOUT.as_secs());
+            process::exit(1);
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+}
+
 fn start_daemon() {
-    println!("Starting Claudy daemon...");
-    println!("(Use 'cargo tauri dev' to run the full app)");
-    // TODO: Start the Tauri app or connect to running instance
+    let _lock = match InstanceLock::acquire(true) {
+        Ok(lock) => lock,
+        Err(LockError::Running(running)) => {
+            match running {
+                Some(running) => eprintln!("Claudy is already running (pid {}).", running.pid),
+                None => eprintln!("Claudy is already running."),
+            }
+            process::exit(1);
+        }
+        Err(LockError::Io(e)) => {
+            eprintln!("Failed to take the instance lock: {}", e);
+            process::exit(1);
+        }
+    };
+
+    let cfg = config::load_config();
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("Failed to start async runtime");
+
+    let backend = Backend::new(&cfg, runtime.handle());
+    // Serving is the daemon's whole point, so let the supervisor know
+    backend.start_servers(&cfg, runtime.handle(), |error| {
+        eprintln!("{}", error);
+        backend::cleanup();
+        process::exit(1);
+    });
+    let mut requests = backend.context.app.subscribe();
+
+    let pipeline = match backend.spawn_pipeline(runtime.handle()) {
+        Ok(handle) => handle,
+        Err(e) => {
+            eprintln!("Failed to watch transcripts: {}", e);
+            backend::cleanup();
+            process::exit(1);
+        }
+    };
+    println!(
+        "Claudy daemon running (pid {}), watching {} project(s)",
+        std::process::id(),
+        cfg.projects.registered.len()
+    );
+
+    runtime.block_on(async {
+        tokio::select! {
+            _ = shutdown_signal() => {}
+            _ = quit_requested(&mut requests) => {}
+        }
+        println!("Shutting down...");
+        pipeline.shutdown().await;
+    });
+    backend::cleanup();
+}
+
+/// `shutdown` from a client; window requests don't apply without a window
+async fn quit_requested(requests: &mut broadcast::Receiver<AppRequest>) {
+    loop {
+        match requests.recv().await {
+            Ok(AppRequest::Quit) | Err(broadcast::error::RecvError::Closed) => return,
+            Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+        }
+    }
+}
+
+/// Ctrl-C, or SIGTERM from a service manager
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut terminate = signal(SignalKind::terminate()).expect("Failed to listen for SIGTERM");
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_duration_combines_units() {
+        assert_eq!(parse_duration("1h30m"), Ok(Duration::from_secs(5400)));
+        assert_eq!(parse_duration("90s"), Ok(Duration::from_secs(90)));
+    }
+
+    #[test]
+    fn parse_duration_reads_bare_numbers_as_minutes() {
+        assert_eq!(parse_duration("45"), Ok(Duration::from_secs(2700)));
+        assert_eq!(parse_duration("1h15"), Ok(Duration::from_secs(4500)));
+    }
+
+    #[test]
+    fn parse_duration_rejects_zero_and_garbage() {
+        assert!(parse_duration("0").is_err());
+        assert!(parse_duration("0m").is_err());
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("2d").is_err());
+        assert!(parse_duration("h").is_err());
+    }
+
+    #[test]
+    fn parse_duration_rejects_overflow() {
+        assert!(parse_duration("99999999999999999h").is_err());
+        assert!(parse_duration("18446744073709551615s1s").is_err());
+        assert!(parse_duration("99999999999999999999").is_err());
+    }
 }
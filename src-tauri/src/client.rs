@@ -0,0 +1,181 @@
+//! Blocking client for the running app's control socket or WebSocket
+//! server, used by the CLI
+
+use serde_json::Value;
+use std::fmt;
+use std::io;
+use tokio_tungstenite::tungstenite::{self, Message};
+
+use crate::presence::CLI_ROLE;
+use crate::protocol::{ClientMessage, Command, ServerBody, ServerMessage, Subscription};
+use crate::runtime;
+
+#[derive(Debug)]
+pub enum ClientError {
+    /// No control socket or server.json, or no token to present
+    NotRunning,
+    Connection(Box<tungstenite::Error>),
+    Io(io::Error),
+    /// The server answered with an `error` message
+    Rejected(String),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::NotRunning => write!(f, "Claudy is not running"),
+            ClientError::Connection(e) => write!(f, "connection failed: {}", e),
+            ClientError::Io(e) => write!(f, "{}", e),
+            ClientError::Rejected(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<io::Error> for ClientError {
+    fn from(e: io::Error) -> Self {
+        ClientError::Io(e)
+    }
+}
+
+impl From<tungstenite::Error> for ClientError {
+    fn from(e: tungstenite::Error) -> Self {
+        ClientError::Connection(Box::new(e))
+    }
+}
+
+/// Send a single command and wait for its reply, over the control socket
+/// when it is available and the WebSocket server otherwise. The connection
+/// introduces itself as the CLI, so it doesn't show up in `list_clients`.
+pub fn request(command: Command) -> Result<Value, ClientError> {
+    let hello = ClientMessage::command(
+        None,
+        Command::Hello {
+            name: Some("claudy-cli".to_string()),
+            role: Some(CLI_ROLE.to_string()),
+        },
+    );
+    let message = ClientMessage::command(Some(Value::from(1)), command);
+
+    #[cfg(unix)]
+    if let Ok(stream) = std::os::unix::net::UnixStream::connect(runtime::socket_path()) {
+        return request_socket(stream, &hello, &message);
+    }
+
+    request_websocket(&hello, &message)
+}
+
+#[cfg(unix)]
+fn request_socket(
+    stream: std::os::unix::net::UnixStream,
+    hello: &ClientMessage,
+    message: &ClientMessage,
+) -> Result<Value, ClientError> {
+    use std::io::{BufRead, BufReader, Write};
+
+    for message in [hello, message] {
+        let mut line = serde_json::to_string(message).unwrap_or_default();
+        line.push('\n');
+        (&stream).write_all(line.as_bytes())?;
+    }
+
+    for line in BufReader::new(&stream).lines() {
+        if let Some(result) = reply_to(message, &line?) {
+            return result;
+        }
+    }
+    Err(ClientError::Io(io::ErrorKind::UnexpectedEof.into()))
+}
+
+fn request_websocket(hello: &ClientMessage, message: &ClientMessage) -> Result<Value, ClientError> {
+    let info = runtime::read_server_info().ok_or(ClientError::NotRunning)?;
+    let tokens = runtime::read_tokens().ok_or(ClientError::NotRunning)?;
+
+    let url = format!("{}/?token={}", info.ws_url(), tokens.control);
+    let (mut socket, _) = tungstenite::connect(url)?;
+
+    for message in [hello, message] {
+        let json = serde_json::to_string(message).unwrap_or_default();
+        socket.send(Message::Text(json))?;
+    }
+
+    loop {
+        let Message::Text(text) = socket.read()? else {
+            continue;
+        };
+        if let Some(result) = reply_to(message, &text) {
+            let _ = socket.close(None);
+            return result;
+        }
+    }
+}
+
+/// Follow broadcasts matching `subscription`, starting with a fresh `state`,
+/// until the app goes away
+pub fn watch(subscription: Subscription, mut on_message: impl FnMut(ServerMessage)) -> Result<(), ClientError> {
+    let messages = [
+        ClientMessage::command(Some(Value::from(1)), Command::Subscribe(subscription)),
+        ClientMessage::command(Some(Value::from(2)), Command::Resync),
+    ];
+    let resync_id = messages[1].id.clone();
+
+    // Anything before the resync reply, like the intro state, is stale
+    let mut synced = false;
+    let mut handle = |text: &str| {
+        let Ok(message) = serde_json::from_str::<ServerMessage>(text) else {
+            return;
+        };
+        if message.id == resync_id {
+            synced = true;
+        } else if !synced || message.id.is_some() {
+            return;
+        }
+        on_message(message);
+    };
+
+    #[cfg(unix)]
+    if let Ok(stream) = std::os::unix::net::UnixStream::connect(runtime::socket_path()) {
+        use std::io::{BufRead, BufReader, Write};
+
+        for message in &messages {
+            let mut line = serde_json::to_string(message).unwrap_or_default();
+            line.push('\n');
+            (&stream).write_all(line.as_bytes())?;
+        }
+        for line in BufReader::new(&stream).lines() {
+            handle(&line?);
+        }
+        return Ok(());
+    }
+
+    let info = runtime::read_server_info().ok_or(ClientError::NotRunning)?;
+    let tokens = runtime::read_tokens().ok_or(ClientError::NotRunning)?;
+    let url = format!("{}/?token={}", info.ws_url(), tokens.read);
+    let (mut socket, _) = tungstenite::connect(url)?;
+
+    for message in &messages {
+        socket.send(Message::Text(serde_json::to_string(message).unwrap_or_default()))?;
+    }
+    loop {
+        match socket.read() {
+            Ok(Message::Text(text)) => handle(&text),
+            Ok(_) => {}
+            Err(tungstenite::Error::ConnectionClosed) => return Ok(()),
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// The outcome of `message`, if `text` is its reply. State broadcasts and
+/// other messages are skipped.
+fn reply_to(message: &ClientMessage, text: &str) -> Option<Result<Value, ClientError>> {
+    let reply = serde_json::from_str::<ServerMessage>(text).ok()?;
+    if reply.id != message.id {
+        return None;
+    }
+
+    match reply.body {
+        ServerBody::Response { result } => Some(Ok(result)),
+        ServerBody::Error { message, .. } => Some(Err(ClientError::Rejected(message))),
+        _ => None,
+    }
+}
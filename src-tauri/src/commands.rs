@@ -0,0 +1,187 @@
+//! Execution of protocol commands against the shared app state
+
+use serde_json::{json, Value};
+use std::path::Path;
+
+use crate::auth::Scope;
+use crate::config;
+use crate::history::SharedHistory;
+use crate::instance::{AppRequest, AppRequests};
+use crate::pipeline::PipelineController;
+use crate::presence::ClientRegistry;
+use crate::protocol::{Command, ErrorCode, ProtocolError};
+use crate::quiet::Quiet;
+use crate::runtime;
+use crate::speech::Speaker;
+use crate::state::SharedState;
+use crate::watcher::path_to_slug;
+use crate::websocket::{broadcast_state, StateBroadcaster};
+
+/// Default number of entries returned by `get_history`
+const DEFAULT_HISTORY_LIMIT: usize = 50;
+
+/// Everything commands need, shared by every connection
+#[derive(Clone)]
+pub struct ServerContext {
+    pub state: SharedState,
+    pub broadcaster: StateBroadcaster,
+    pub history: SharedHistory,
+    pub speaker: Speaker,
+    pub clients: ClientRegistry,
+    pub pipeline: PipelineController,
+    /// Requests for the app window or the daemon
+    pub app: AppRequests,
+    pub quiet: Quiet,
+}
+
+impl ServerContext {
+    /// Run a command on behalf of a client with the given scope. Connection
+    /// specific commands (`subscribe`, `hello`, `resync`) are handled by the transport.
+    pub async fn execute(&self, command: Command, scope: Scope) -> Result<Value, ProtocolError> {
+        if command.is_control() && scope != Scope::Control {
+            return Err(ProtocolError::new(
+                ErrorCode::Forbidden,
+                "this command requires the control token",
+            ));
+        }
+
+        match command {
+            Command::SetState {
+                state,
+                suppress_comments,
+            } => {
+                let mut snapshot = {
+                    let mut s = self.state.lock().unwrap();
+                    s.current_state = state;
+                    s.clone()
+                };
+                // Only meaningful for this broadcast
+                snapshot.suppress_comments = suppress_comments;
+                broadcast_state(&self.broadcaster, &snapshot);
+                Ok(Value::Null)
+            }
+            Command::Say(request) => match self.speaker.say(request).await {
+                Some(outcome) => Ok(json!(outcome)),
+                None => Err(ProtocolError::new(ErrorCode::Internal, "speech queue stopped")),
+            },
+            Command::FocusProject { project } => {
+                let snapshot = {
+                    let mut s = self.state.lock().unwrap();
+                    let slug = resolve_project(&s.active_projects, &project).ok_or_else(|| {
+                        ProtocolError::new(
+                            ErrorCode::NotFound,
+                            format!("no active project {}", project),
+                        )
+                    })?;
+                    s.focused_project = Some(slug);
+                    s.clone()
+                };
+                broadcast_state(&self.broadcaster, &snapshot);
+                Ok(Value::Null)
+            }
+            Command::Subscribe(_) | Command::Hello { .. } | Command::Resync => Err(ProtocolError::new(
+                ErrorCode::InvalidMessage,
+                "only available on streaming connections",
+            )),
+            Command::Status => {
+                let state = self.state.lock().unwrap().clone();
+                Ok(json!({
+                    "pid": std::process::id(),
+                    "state": state.current_state,
+                    "active_projects": state.active_projects,
+                    "focused_project": state.focused_project,
+                    "paused": self.pipeline.is_paused(),
+                    "clients": self.clients.list().len(),
+                    "quiet": self.quiet.status(),
+                    "server": runtime::read_server_info(),
+                }))
+            }
+            Command::Pause { paused } => {
+                self.pipeline.set_paused(paused);
+                Ok(json!({ "paused": paused }))
+            }
+            Command::Reload => {
+                self.pipeline.reload_config();
+                Ok(Value::Null)
+            }
+            Command::ListClients => Ok(json!(self.clients.list())),
+            Command::KickClient { id } => {
+                if self.clients.kick(id) {
+                    Ok(Value::Null)
+                } else {
+                    Err(ProtocolError::new(
+                        ErrorCode::NotFound,
+                        format!("no client {}", id),
+                    ))
+                }
+            }
+            Command::GetHistory { limit } => {
+                let entries = self
+                    .history
+                    .lock()
+                    .unwrap()
+                    .recent(limit.unwrap_or(DEFAULT_HISTORY_LIMIT));
+                Ok(json!(entries))
+            }
+            Command::GetConfig => serde_json::to_value(config::load_config())
+                .map_err(|e| ProtocolError::new(ErrorCode::Internal, e.to_string())),
+            Command::RegisterProject { path } => {
+                let registered = config::register_project(&path)
+                    .map_err(|e| ProtocolError::new(ErrorCode::Internal, e.to_string()))?;
+                if registered {
+                    self.pipeline.reload_config();
+                }
+                Ok(json!({ "registered": registered }))
+            }
+            Command::SetSize { size } => {
+                if config::parse_size(&size).is_none() {
+                    return Err(ProtocolError::new(
+                        ErrorCode::InvalidMessage,
+                        format!("invalid size {}", size),
+                    ));
+                }
+                let mut cfg = config::load_config();
+                cfg.appearance.size = size;
+                config::save_config(&cfg)
+                    .map_err(|e| ProtocolError::new(ErrorCode::Internal, e.to_string()))?;
+                self.request_app(AppRequest::ApplySize)
+            }
+            Command::SetWindowMode { mode, enabled } => {
+                let mut cfg = config::load_config();
+                cfg.window.set(mode, enabled);
+                config::save_config(&cfg)
+                    .map_err(|e| ProtocolError::new(ErrorCode::Internal, e.to_string()))?;
+                self.request_app(AppRequest::ApplyWindowModes)?;
+                Ok(json!(cfg.window))
+            }
+            Command::SetDnd { enabled, duration } => {
+                let status = self
+                    .quiet
+                    .set_dnd(enabled, duration.map(std::time::Duration::from_secs));
+                Ok(json!(status))
+            }
+            Command::ShowWindow => self.request_app(AppRequest::ShowWindow),
+            Command::OpenConfig => self.request_app(AppRequest::OpenConfig),
+            Command::Shutdown => {
+                self.request_app(AppRequest::Quit)?;
+                Ok(json!({ "pid": std::process::id() }))
+            }
+        }
+    }
+
+    fn request_app(&self, request: AppRequest) -> Result<Value, ProtocolError> {
+        self.app
+            .send(request)
+            .map(|_| Value::Null)
+            .map_err(|_| ProtocolError::new(ErrorCode::Internal, "nothing handles app requests"))
+    }
+}
+
+/// Match a project given as slug or as path against the active projects
+fn resolve_project(active_projects: &[String], project: &str) -> Option<String> {
+    let slug = path_to_slug(Path::new(project));
+    active_projects
+        .iter()
+        .find(|p| *p == project || **p == slug)
+        .cloned()
+}
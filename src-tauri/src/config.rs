@@ -1,4 +1,6 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 
@@ -9,11 +11,12 @@ use std::path::PathBuf;
 // The Configuration UI (tray menu -> Configuration) currently supports:
 //   - [x] Appearance: background color
 //   - [x] Projects: view/remove registered projects
+//   - [x] Behavior: auto_start
+//   - [x] Appearance: size
 //
 // Planned expansions for the Configuration UI:
 //
 // BEHAVIOR SECTION:
-//   - [ ] auto_start: Toggle auto-start on system boot
 //   - [ ] idle_timeout: Slider for idle timeout (seconds before idle state)
 //   - [ ] sleepy_timeout: Slider for sleepy timeout (seconds before sleep state)
 //
@@ -23,7 +26,6 @@ use std::path::PathBuf;
 //   - [ ] bubble_duration: Slider for speech bubble duration (seconds)
 //
 // APPEARANCE SECTION (additional):
-//   - [ ] size: Dropdown for Claudy size (small/medium/large)
 //   - [ ] theme: Dropdown for theme (auto/light/dark) - requires theme implementation
 //
 // POSITION SECTION:
@@ -43,11 +45,19 @@ pub struct Config {
     #[serde(default)]
     pub appearance: AppearanceConfig,
     #[serde(default)]
+    pub window: WindowConfig,
+    #[serde(default)]
     pub notifications: NotificationConfig,
     #[serde(default)]
     pub behavior: BehaviorConfig,
     #[serde(default)]
     pub projects: ProjectsConfig,
+    #[serde(default)]
+    pub server: ServerConfig,
+    #[serde(default)]
+    pub quiet: QuietConfig,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sinks: Vec<SinkConfig>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -58,10 +68,15 @@ pub struct PositionConfig {
     pub y: i32,
     #[serde(default = "default_anchor")]
     pub anchor: String,
+    /// "primary", a monitor name or index, "cursor" to follow the mouse
+    /// pointer or "focused" to follow the focused window
+    #[serde(default = "default_monitor")]
+    pub monitor: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AppearanceConfig {
+    /// "small", "medium", "large" or a scale factor such as "1.25"
     #[serde(default = "default_size")]
     pub size: String,
     #[serde(default = "default_theme")]
@@ -70,6 +85,61 @@ pub struct AppearanceConfig {
     pub background: Option<String>, // e.g., "#1a1a2e" or None for transparent
 }
 
+/// How the mascot window behaves on the desktop
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct WindowConfig {
+    #[serde(default = "default_true")]
+    pub always_on_top: bool,
+    /// Let clicks pass through to the windows underneath
+    #[serde(default)]
+    pub click_through: bool,
+    #[serde(default = "default_true")]
+    pub skip_taskbar: bool,
+    /// Show on every workspace (virtual desktop)
+    #[serde(default)]
+    pub all_workspaces: bool,
+}
+
+/// One of the `[window]` toggles
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowMode {
+    AlwaysOnTop,
+    ClickThrough,
+    SkipTaskbar,
+    AllWorkspaces,
+}
+
+impl WindowMode {
+    pub const ALL: [WindowMode; 4] = [
+        WindowMode::AlwaysOnTop,
+        WindowMode::ClickThrough,
+        WindowMode::SkipTaskbar,
+        WindowMode::AllWorkspaces,
+    ];
+}
+
+impl WindowConfig {
+    pub fn get(&self, mode: WindowMode) -> bool {
+        match mode {
+            WindowMode::AlwaysOnTop => self.always_on_top,
+            WindowMode::ClickThrough => self.click_through,
+            WindowMode::SkipTaskbar => self.skip_taskbar,
+            WindowMode::AllWorkspaces => self.all_workspaces,
+        }
+    }
+
+    pub fn set(&mut self, mode: WindowMode, enabled: bool) {
+        let flag = match mode {
+            WindowMode::AlwaysOnTop => &mut self.always_on_top,
+            WindowMode::ClickThrough => &mut self.click_through,
+            WindowMode::SkipTaskbar => &mut self.skip_taskbar,
+            WindowMode::AllWorkspaces => &mut self.all_workspaces,
+        };
+        *flag = enabled;
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct NotificationConfig {
     #[serde(default = "default_true")]
@@ -78,16 +148,37 @@ pub struct NotificationConfig {
     pub os_notifications: bool,
     #[serde(default = "default_bubble_duration")]
     pub bubble_duration: u32,
+    /// Notify when a turn took at least this many seconds (0 disables)
+    #[serde(default = "default_turn_finished_after")]
+    pub turn_finished_after: u32,
+    #[serde(default = "default_true")]
+    pub on_error: bool,
+    #[serde(default = "default_true")]
+    pub on_needs_attention: bool,
+    #[serde(default)]
+    pub on_background_session: bool,
+    /// Skip notifications for the project whose window is focused
+    #[serde(default = "default_true")]
+    pub skip_focused_project: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct BehaviorConfig {
+    /// Mirrors whether an autostart entry is installed; change it with
+    /// `claudy-cli autostart` or the config window
     #[serde(default)]
     pub auto_start: bool,
     #[serde(default = "default_idle_timeout")]
     pub idle_timeout: u32,
     #[serde(default = "default_sleepy_timeout")]
     pub sleepy_timeout: u32,
+    /// Hide after `sleepy_timeout` seconds without activity, until the next
+    /// session start or user message
+    #[serde(default)]
+    pub auto_hide_idle: bool,
+    /// Hide while a full-screen window is focused on the mascot's monitor
+    #[serde(default)]
+    pub hide_on_fullscreen: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
@@ -96,6 +187,120 @@ pub struct ProjectsConfig {
     pub registered: Vec<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ServerConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default = "default_server_bind")]
+    pub bind: String,
+    #[serde(default = "default_server_port")]
+    pub port: u16,
+    /// When the port is taken: "next" tries the following ports, "fail" gives up
+    #[serde(default = "default_port_fallback")]
+    pub port_fallback: String,
+    /// Browser origins allowed to connect ("*" allows any). Clients that send
+    /// no Origin header, like scripts, are always allowed.
+    #[serde(default = "default_allowed_origins")]
+    pub allowed_origins: Vec<String>,
+    /// Let clients without a token connect read-only
+    #[serde(default)]
+    pub anonymous_read: bool,
+    /// Also listen on the control socket, `$XDG_RUNTIME_DIR/claudy.sock`
+    #[serde(default = "default_true")]
+    pub socket: bool,
+    /// Broadcasts buffered per client; slower clients skip ahead to a fresh
+    /// snapshot. Takes effect on restart.
+    #[serde(default = "default_queue_size")]
+    pub queue_size: usize,
+}
+
+/// Quiet hours, see [`crate::quiet`]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct QuietConfig {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub schedules: Vec<QuietSchedule>,
+    /// Also stop animating while quiet
+    #[serde(default)]
+    pub freeze_animations: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QuietSchedule {
+    /// Days the quiet time starts on ("mon", "weekdays", "weekends"); every day when empty
+    #[serde(default)]
+    pub days: Vec<String>,
+    /// Start time, "HH:MM"
+    pub from: String,
+    /// End time, "HH:MM"; earlier than `from` to end the next day
+    pub to: String,
+}
+
+/// An external consumer of state changes, configured as a `[[sinks]]` entry.
+/// String fields marked as templates accept `{{placeholders}}` such as
+/// `{{event}}`, `{{state}}`, `{{project}}`, `{{tool}}` and `{{file_path}}`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SinkConfig {
+    #[serde(flatten)]
+    pub kind: SinkKind,
+    /// Event kinds to forward (e.g. "Stop", "ToolUse"); empty forwards all
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<String>,
+    /// Projects to forward, as registered paths; empty forwards all
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub projects: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SinkKind {
+    Webhook(WebhookSinkConfig),
+    Command(CommandSinkConfig),
+    Mqtt(MqttSinkConfig),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct WebhookSinkConfig {
+    pub url: String,
+    #[serde(default = "default_webhook_method")]
+    pub method: String,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// JSON body template; defaults to the full event payload
+    #[serde(default)]
+    pub body: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CommandSinkConfig {
+    pub command: String,
+    /// Argument templates
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MqttSinkConfig {
+    pub host: String,
+    #[serde(default = "default_mqtt_port")]
+    pub port: u16,
+    #[serde(default = "default_mqtt_client_id")]
+    pub client_id: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Topic template
+    #[serde(default = "default_mqtt_topic")]
+    pub topic: String,
+    /// Payload template; defaults to the full event payload
+    #[serde(default)]
+    pub payload: Option<String>,
+    #[serde(default)]
+    pub qos: u8,
+    #[serde(default)]
+    pub retain: bool,
+}
+
 // Default functions
 fn default_x() -> i32 {
     100
@@ -106,6 +311,9 @@ fn default_y() -> i32 {
 fn default_anchor() -> String {
     "bottom-right".to_string()
 }
+fn default_monitor() -> String {
+    "primary".to_string()
+}
 fn default_size() -> String {
     "medium".to_string()
 }
@@ -118,21 +326,65 @@ fn default_true() -> bool {
 fn default_bubble_duration() -> u32 {
     5
 }
+fn default_turn_finished_after() -> u32 {
+    30
+}
 fn default_idle_timeout() -> u32 {
     30
 }
 fn default_sleepy_timeout() -> u32 {
     300
 }
+fn default_server_bind() -> String {
+    "127.0.0.1".to_string()
+}
+fn default_server_port() -> u16 {
+    3695
+}
+fn default_port_fallback() -> String {
+    "next".to_string()
+}
+fn default_allowed_origins() -> Vec<String> {
+    [
+        "tauri://localhost",
+        "http://tauri.localhost",
+        "http://localhost:5173",
+        "http://127.0.0.1:5173",
+    ]
+    .iter()
+    .map(|o| o.to_string())
+    .collect()
+}
+fn default_queue_size() -> usize {
+    64
+}
+fn default_webhook_method() -> String {
+    "POST".to_string()
+}
+fn default_mqtt_port() -> u16 {
+    1883
+}
+fn default_mqtt_client_id() -> String {
+    "claudy".to_string()
+}
+fn default_mqtt_topic() -> String {
+    "claudy/{{project}}".to_string()
+}
 
+// Spelled out like the sections' defaults below
+#[allow(clippy::derivable_impls)]
 impl Default for Config {
     fn default() -> Self {
         Self {
             position: PositionConfig::default(),
             appearance: AppearanceConfig::default(),
+            window: WindowConfig::default(),
             notifications: NotificationConfig::default(),
             behavior: BehaviorConfig::default(),
             projects: ProjectsConfig::default(),
+            server: ServerConfig::default(),
+            quiet: QuietConfig::default(),
+            sinks: Vec::new(),
         }
     }
 }
@@ -143,6 +395,7 @@ impl Default for PositionConfig {
             x: default_x(),
             y: default_y(),
             anchor: default_anchor(),
+            monitor: default_monitor(),
         }
     }
 }
@@ -157,12 +410,43 @@ impl Default for AppearanceConfig {
     }
 }
 
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            always_on_top: default_true(),
+            click_through: false,
+            skip_taskbar: default_true(),
+            all_workspaces: false,
+        }
+    }
+}
+
 impl Default for NotificationConfig {
     fn default() -> Self {
         Self {
             sound: default_true(),
             os_notifications: default_true(),
             bubble_duration: default_bubble_duration(),
+            turn_finished_after: default_turn_finished_after(),
+            on_error: default_true(),
+            on_needs_attention: default_true(),
+            on_background_session: false,
+            skip_focused_project: default_true(),
+        }
+    }
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_true(),
+            bind: default_server_bind(),
+            port: default_server_port(),
+            port_fallback: default_port_fallback(),
+            allowed_origins: default_allowed_origins(),
+            anonymous_read: false,
+            socket: default_true(),
+            queue_size: default_queue_size(),
         }
     }
 }
@@ -173,10 +457,32 @@ impl Default for BehaviorConfig {
             auto_start: false,
             idle_timeout: default_idle_timeout(),
             sleepy_timeout: default_sleepy_timeout(),
+            auto_hide_idle: false,
+            hide_on_fullscreen: false,
         }
     }
 }
 
+impl AppearanceConfig {
+    /// Window scale for `size`, 1.0 when it isn't valid
+    pub fn scale(&self) -> f64 {
+        parse_size(&self.size).unwrap_or(1.0)
+    }
+}
+
+/// Scale factor for a size preset or number, between 0.25 and 4
+pub fn parse_size(size: &str) -> Option<f64> {
+    match size.trim() {
+        "small" => Some(0.75),
+        "medium" => Some(1.0),
+        "large" => Some(1.5),
+        other => other
+            .parse::<f64>()
+            .ok()
+            .filter(|scale| (0.25..=4.0).contains(scale)),
+    }
+}
+
 pub fn config_path() -> PathBuf {
     let dirs = directories::ProjectDirs::from("com", "claudy", "claudy")
         .expect("Could not determine config directory");
@@ -202,3 +508,15 @@ pub fn save_config(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
     fs::write(path, content)?;
     Ok(())
 }
+
+/// Add a project directory to the config unless it's already there.
+/// Returns whether it was added.
+pub fn register_project(path: &str) -> Result<bool, Box<dyn std::error::Error>> {
+    let mut config = load_config();
+    if config.projects.registered.iter().any(|p| p == path) {
+        return Ok(false);
+    }
+    config.projects.registered.push(path.to_string());
+    save_config(&config)?;
+    Ok(true)
+}
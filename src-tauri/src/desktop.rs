@@ -0,0 +1,56 @@
+//! Opening files and picking folders through the platform's own tools
+//! (`xdg-open` and `zenity` or `kdialog` on Linux). These block until done,
+//! so don't call them from async tasks directly.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Open a file or directory with the default application
+pub fn open_path(path: &Path) -> io::Result<()> {
+    #[cfg(target_os = "linux")]
+    let mut command = Command::new("xdg-open");
+    #[cfg(target_os = "macos")]
+    let mut command = Command::new("open");
+    #[cfg(windows)]
+    let mut command = {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    };
+
+    command.arg(path).spawn().map(|_| ())
+}
+
+/// Ask the user for a directory. `None` when cancelled or when no picker is
+/// available.
+pub fn pick_folder(title: &str) -> Option<PathBuf> {
+    #[cfg(target_os = "linux")]
+    {
+        let zenity = ["--file-selection", "--directory", "--title", title];
+        let home = dirs::home_dir().unwrap_or_default();
+        let home = home.to_string_lossy();
+        let kdialog = ["--getexistingdirectory", home.as_ref(), "--title", title];
+        run("zenity", &zenity).or_else(|| run("kdialog", &kdialog))
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let script = format!("POSIX path of (choose folder with prompt {:?})", title);
+        run("osascript", &["-e", &script])
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    {
+        let _ = title;
+        None
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn run(program: &str, args: &[&str]) -> Option<PathBuf> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!path.is_empty()).then(|| PathBuf::from(path))
+}
@@ -0,0 +1,72 @@
+//! Best-effort lookup of the desktop's focused window, used to avoid
+//! bothering the user about what they are already looking at.
+
+use std::process::Command;
+
+/// Title of the currently focused window, if the platform tooling is available
+/// (`xdotool` on X11, AppleScript on macOS). Runs a subprocess, so don't call
+/// it from async tasks directly.
+pub fn active_window_title() -> Option<String> {
+    #[cfg(target_os = "linux")]
+    {
+        run("xdotool", &["getactivewindow", "getwindowname"])
+    }
+    #[cfg(target_os = "macos")]
+    {
+        run(
+            "osascript",
+            &[
+                "-e",
+                "tell application \"System Events\" to get name of first window of (first application process whose frontmost is true)",
+            ],
+        )
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    {
+        None
+    }
+}
+
+/// Position and size (x, y, width, height) of the focused window in physical
+/// screen coordinates, if the platform tooling is available (`xdotool` on
+/// X11). Runs a subprocess.
+pub fn active_window_geometry() -> Option<(f64, f64, f64, f64)> {
+    #[cfg(target_os = "linux")]
+    {
+        let geometry = run("xdotool", &["getactivewindow", "getwindowgeometry", "--shell"])?;
+        let value = |key: &str| {
+            geometry.lines().find_map(|line| {
+                line.strip_prefix(key)?.strip_prefix('=')?.parse::<f64>().ok()
+            })
+        };
+        Some((value("X")?, value("Y")?, value("WIDTH")?, value("HEIGHT")?))
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
+/// Center of the focused window, see [`active_window_geometry`]
+pub fn active_window_center() -> Option<(f64, f64)> {
+    let (x, y, width, height) = active_window_geometry()?;
+    Some((x + width / 2.0, y + height / 2.0))
+}
+
+/// Whether the focused window looks like it belongs to the given project
+/// directory. Terminals and editors usually put the directory name in their
+/// title.
+pub fn is_project_focused(project_dir_name: &str) -> bool {
+    !project_dir_name.is_empty()
+        && active_window_title().is_some_and(|title| title.contains(project_dir_name))
+}
+
+#[allow(dead_code)] // Unused on platforms without a probe
+fn run(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let text = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
@@ -0,0 +1,70 @@
+//! Ring buffer of recent events, served to clients on request
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::pipeline::{Sink, StateUpdate};
+use crate::watcher::ClaudeEvent;
+
+/// Number of events kept
+const HISTORY_CAPACITY: usize = 200;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct HistoryEntry {
+    /// Milliseconds since the Unix epoch
+    pub timestamp: u64,
+    pub event: ClaudeEvent,
+    /// Mascot state after the event was applied
+    pub state: String,
+}
+
+#[derive(Debug, Default)]
+pub struct EventHistory {
+    entries: VecDeque<HistoryEntry>,
+}
+
+pub type SharedHistory = Arc<Mutex<EventHistory>>;
+
+impl EventHistory {
+    pub fn push(&mut self, entry: HistoryEntry) {
+        if self.entries.len() == HISTORY_CAPACITY {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    /// The most recent `limit` entries, oldest first
+    pub fn recent(&self, limit: usize) -> Vec<HistoryEntry> {
+        let skip = self.entries.len().saturating_sub(limit);
+        self.entries.iter().skip(skip).cloned().collect()
+    }
+}
+
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Pipeline sink recording every update into the history
+pub struct HistorySink(SharedHistory);
+
+impl HistorySink {
+    pub fn new(history: SharedHistory) -> Self {
+        Self(history)
+    }
+}
+
+impl Sink for HistorySink {
+    fn handle(&self, update: &StateUpdate) {
+        self.0.lock().unwrap().push(HistoryEntry {
+            timestamp: now_millis(),
+            event: update.event.clone(),
+            state: update.state.current_state.clone(),
+        });
+    }
+}
@@ -0,0 +1,124 @@
+//! Single running instance. The app and the daemon hold a lock on
+//! `instance.lock` in the runtime dir for as long as they run; a second
+//! launch finds it taken and hands its intent to the running instance.
+
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+use crate::runtime;
+
+const LOCK_FILE: &str = "instance.lock";
+
+/// Tries at taking the lock before giving up, so that `is_running` probing
+/// it at that moment doesn't look like a running instance
+const LOCK_ATTEMPTS: u32 = 5;
+const LOCK_RETRY: Duration = Duration::from_millis(20);
+
+/// The process holding the lock, written into the lock file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstanceInfo {
+    pub pid: u32,
+    /// Executable to launch again on restart
+    pub exe: PathBuf,
+    /// Started with `claudy-cli daemon`, without a window
+    pub headless: bool,
+}
+
+#[derive(Debug)]
+pub enum LockError {
+    /// Another instance holds the lock; its info if it could be read
+    Running(Option<InstanceInfo>),
+    Io(io::Error),
+}
+
+/// Held for the lifetime of the instance; the OS releases it on exit, even
+/// after a crash
+pub struct InstanceLock {
+    file: File,
+}
+
+impl InstanceLock {
+    pub fn acquire(headless: bool) -> Result<Self, LockError> {
+        let dir = runtime::ensure_runtime_dir().map_err(LockError::Io)?;
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(dir.join(LOCK_FILE))
+            .map_err(LockError::Io)?;
+
+        let mut attempts = 0;
+        loop {
+            match file.try_lock() {
+                Ok(()) => break,
+                Err(TryLockError::WouldBlock) if attempts + 1 < LOCK_ATTEMPTS => {
+                    attempts += 1;
+                    thread::sleep(LOCK_RETRY);
+                }
+                Err(TryLockError::WouldBlock) => return Err(LockError::Running(read_instance())),
+                Err(TryLockError::Error(e)) => return Err(LockError::Io(e)),
+            }
+        }
+
+        let info = InstanceInfo {
+            pid: std::process::id(),
+            exe: std::env::current_exe().map_err(LockError::Io)?,
+            headless,
+        };
+        let content = serde_json::to_string(&info).map_err(|e| LockError::Io(io::Error::other(e)))?;
+        file.set_len(0).map_err(LockError::Io)?;
+        file.write_all(content.as_bytes()).map_err(LockError::Io)?;
+
+        Ok(Self { file })
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        // An empty lock file means nobody runs, without probing the lock
+        let _ = self.file.set_len(0);
+    }
+}
+
+/// Whether some process holds the lock
+pub fn is_running() -> bool {
+    running_instance().is_some()
+}
+
+/// The running instance, if any
+pub fn running_instance() -> Option<InstanceInfo> {
+    // An instance empties the file when it lets go of the lock. Only info
+    // left behind by a crash needs the lock probed, which `acquire` rides out.
+    let info = read_instance()?;
+    let file = File::open(runtime::runtime_dir().join(LOCK_FILE)).ok()?;
+    matches!(file.try_lock(), Err(TryLockError::WouldBlock)).then_some(info)
+}
+
+fn read_instance() -> Option<InstanceInfo> {
+    let content = fs::read_to_string(runtime::runtime_dir().join(LOCK_FILE)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Requests for the process hosting the backend, sent by commands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AppRequest {
+    ShowWindow,
+    OpenConfig,
+    /// Apply the `[window]` toggles from the config
+    ApplyWindowModes,
+    /// Apply `appearance.size` from the config
+    ApplySize,
+    Quit,
+}
+
+pub type AppRequests = broadcast::Sender<AppRequest>;
+
+pub fn new_app_requests() -> AppRequests {
+    broadcast::channel(8).0
+}
@@ -0,0 +1,117 @@
+//! Control socket at `$XDG_RUNTIME_DIR/claudy.sock` for local tooling. It
+//! speaks the WebSocket protocol as line-delimited JSON, one message per
+//! line. Only the current user can open the socket, so every client gets the
+//! control scope.
+//!
+//! Unlike WebSocket clients, socket clients receive no broadcasts until they
+//! `subscribe`.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::broadcast;
+
+use crate::auth::Scope;
+use crate::commands::ServerContext;
+use crate::protocol::Subscription;
+use crate::runtime;
+use crate::websocket::{handle_text, render, snapshot};
+
+/// Whether this process owns the socket file
+static BOUND: AtomicBool = AtomicBool::new(false);
+
+/// Listen on the control socket and serve clients on the current runtime
+pub async fn start_socket(ctx: ServerContext) -> io::Result<PathBuf> {
+    // The socket is only as private as its directory: `$XDG_RUNTIME_DIR` is
+    // the user's own, and the fallback is our 0700 runtime dir
+    let path = runtime::socket_path();
+    if path.starts_with(runtime::runtime_dir()) {
+        runtime::ensure_runtime_dir()?;
+    }
+
+    if path.exists() {
+        // A socket that still accepts connections belongs to a live instance
+        if UnixStream::connect(&path).await.is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("{} is in use", path.display()),
+            ));
+        }
+        fs::remove_file(&path)?;
+    }
+
+    let listener = UnixListener::bind(&path)?;
+    BOUND.store(true, Ordering::Relaxed);
+    eprintln!("[Claudy IPC] Listening on {}", path.display());
+
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    tokio::spawn(handle_connection(stream, ctx.clone()));
+                }
+                Err(e) => {
+                    eprintln!("[Claudy IPC] Accept error: {}", e);
+                }
+            }
+        }
+    });
+
+    Ok(path)
+}
+
+/// Remove the socket on exit, if this process created it
+pub fn remove_socket() {
+    if BOUND.load(Ordering::Relaxed) {
+        let _ = fs::remove_file(runtime::socket_path());
+    }
+}
+
+async fn handle_connection(stream: UnixStream, ctx: ServerContext) {
+    let (read, mut write) = stream.into_split();
+    let mut lines = BufReader::new(read).lines();
+    let mut rx = ctx.broadcaster.subscribe();
+
+    let mut client = ctx.clients.register("unix", Scope::Control);
+    let mut subscription = Subscription {
+        state: false,
+        ..Subscription::default()
+    };
+    let mut seq = 0;
+
+    loop {
+        let reply = tokio::select! {
+            line = lines.next_line() => match line {
+                Ok(Some(line)) if line.trim().is_empty() => None,
+                Ok(Some(line)) => {
+                    Some(handle_text(&ctx, Scope::Control, client.id, &mut subscription, &line).await)
+                }
+                Ok(None) | Err(_) => break,
+            },
+            outgoing = rx.recv() => match outgoing {
+                Ok(message) => render(&subscription, message),
+                Err(broadcast::error::RecvError::Closed) => break,
+                Err(broadcast::error::RecvError::Lagged(_)) if !subscription.state && !subscription.events => None,
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    ctx.clients.update(client.id, |c| c.lagged += n);
+                    seq += n;
+                    Some(snapshot(&ctx))
+                }
+            },
+            _ = &mut client.kicked => break,
+        };
+
+        if let Some(mut message) = reply {
+            seq += 1;
+            message.seq = seq;
+            let mut line = message.to_json();
+            line.push('\n');
+            if write.write_all(line.as_bytes()).await.is_err() {
+                break;
+            }
+        }
+    }
+}
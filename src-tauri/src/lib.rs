@@ -4,3 +4,27 @@ pub mod config;
 pub mod watcher;
 pub mod state;
 pub mod window;
+pub mod pipeline;
+pub mod websocket;
+pub mod sinks;
+pub mod focus;
+pub mod notifications;
+pub mod runtime;
+pub mod auth;
+pub mod protocol;
+pub mod history;
+pub mod commands;
+pub mod speech;
+pub mod client;
+pub mod presence;
+pub mod rest;
+#[cfg(unix)]
+pub mod ipc;
+pub mod statusbar;
+pub mod backend;
+pub mod instance;
+pub mod autostart;
+pub mod autohide;
+pub mod quiet;
+pub mod desktop;
+pub mod tray;
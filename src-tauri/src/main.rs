@@ -1,22 +1,140 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 use tauri::{
-    menu::{Menu, MenuItem},
+    menu::{CheckMenuItem, IsMenuItem, Menu, MenuItem, PredefinedMenuItem},
     tray::TrayIconBuilder,
-    Manager, State, Emitter,
+    Manager, RunEvent, State, Emitter,
 };
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::watch;
 
-mod config;
-mod watcher;
-mod state;
-mod window;
-mod websocket;
+use claudy_lib::autohide::AutoHide;
+use claudy_lib::autostart;
+use claudy_lib::backend::{self, Backend};
+use claudy_lib::client;
+use claudy_lib::config::{self, Config, WindowConfig, WindowMode};
+use claudy_lib::focus;
+use claudy_lib::instance::{AppRequest, InstanceInfo, InstanceLock, LockError};
+use claudy_lib::notifications::{Notice, NotificationRules};
+use claudy_lib::pipeline::{PipelineHandle, Sink, StateUpdate};
+use claudy_lib::protocol::Command;
+use claudy_lib::quiet::{Quiet, QuietStatus};
+use claudy_lib::runtime::{self, ServerInfo};
+use claudy_lib::speech::{Priority, SayOutcome, SayRequest, Speaker};
+use claudy_lib::state::SharedState;
+use claudy_lib::tray::{LiveTray, PROJECT_ITEM_PREFIX};
+use claudy_lib::watcher::{WatcherMetrics, WatcherStats};
+use claudy_lib::window::{self, position_window, reset_position, spawn_positioner, track_drags};
 
-use state::{ClaudyState, SharedState};
-use watcher::SessionWatcher;
-use window::position_window;
-use websocket::StateBroadcaster;
+/// Running pipeline, taken on exit for a clean shutdown
+type PipelineSlot = Mutex<Option<PipelineHandle>>;
+
+/// Tray checkboxes for the `[window]` toggles
+struct ModeItems(Vec<(WindowMode, CheckMenuItem<tauri::Wry>)>);
+
+fn mode_label(mode: WindowMode) -> &'static str {
+    match mode {
+        WindowMode::AlwaysOnTop => "Always on Top",
+        WindowMode::ClickThrough => "Click-Through",
+        WindowMode::SkipTaskbar => "Hide from Taskbar",
+        WindowMode::AllWorkspaces => "On All Workspaces",
+    }
+}
+
+fn mode_item_id(mode: WindowMode) -> String {
+    format!("mode:{:?}", mode)
+}
+
+/// Apply `[window]` to the window and the tray checkboxes
+fn apply_window_modes(app: &tauri::AppHandle) {
+    let modes = config::load_config().window;
+    if let Some(window) = app.get_webview_window("main") {
+        window::apply_modes(&window, &modes);
+    }
+    if let Some(items) = app.try_state::<ModeItems>() {
+        for (mode, item) in &items.0 {
+            let _ = item.set_checked(modes.get(*mode));
+        }
+    }
+}
+
+fn update_window_mode(app: &tauri::AppHandle, mode: WindowMode, enabled: bool) -> Result<WindowConfig, String> {
+    let mut cfg = config::load_config();
+    cfg.window.set(mode, enabled);
+    config::save_config(&cfg).map_err(|e| e.to_string())?;
+    apply_window_modes(app);
+    Ok(cfg.window)
+}
+
+/// Pipeline sink emitting state changes to the Tauri frontend
+struct TauriSink(tauri::AppHandle);
+
+impl Sink for TauriSink {
+    fn handle(&self, update: &StateUpdate) {
+        eprintln!("[Claudy] Emitting state: {}", update.state.current_state);
+        if let Err(e) = self.0.emit("claudy-state-change", &update.state.current_state) {
+            eprintln!("[Claudy] Emit error: {}", e);
+        }
+    }
+}
+
+/// Sound played with notifications when `notifications.sound` is on
+#[cfg(target_os = "linux")]
+const NOTIFICATION_SOUND: &str = "message-new-instant";
+#[cfg(not(target_os = "linux"))]
+const NOTIFICATION_SOUND: &str = "default";
+
+/// Pipeline sink raising OS notifications from `[notifications]` rules, so
+/// they work even while the window is hidden. Silent during quiet time.
+struct NotificationSink {
+    app: tauri::AppHandle,
+    rules: Mutex<NotificationRules>,
+    /// Follows the pipeline's config reloads
+    config: watch::Receiver<Arc<Config>>,
+    quiet: Quiet,
+}
+
+impl Sink for NotificationSink {
+    fn handle(&self, update: &StateUpdate) {
+        let cfg = self.config.borrow().clone();
+        // Always evaluate so turn timing stays accurate while disabled
+        let notice = self.rules.lock().unwrap().evaluate(update, &cfg);
+        let Some(notice) = notice else {
+            return;
+        };
+        if !cfg.notifications.os_notifications || self.quiet.is_quiet() {
+            return;
+        }
+
+        let app = self.app.clone();
+        let rules = cfg.notifications.clone();
+        // The focus probe spawns a process
+        tauri::async_runtime::spawn_blocking(move || {
+            if rules.skip_focused_project && focus::is_project_focused(&notice.project_name) {
+                return;
+            }
+            if let Err(e) = show_notice(&app, &notice, rules.sound) {
+                eprintln!("[Claudy] Notification failed: {}", e);
+            }
+        });
+    }
+}
+
+fn show_notice(app: &tauri::AppHandle, notice: &Notice, sound: bool) -> Result<(), String> {
+    use tauri_plugin_notification::NotificationExt;
+
+    let mut builder = app
+        .notification()
+        .builder()
+        .title(&notice.title)
+        .body(&notice.body);
+    if sound {
+        builder = builder.sound(NOTIFICATION_SOUND);
+    }
+    builder.show().map_err(|e| e.to_string())
+}
 
 #[tauri::command]
 fn get_state(state: State<SharedState>) -> String {
@@ -30,6 +148,11 @@ fn get_active_projects(state: State<SharedState>) -> Vec<String> {
     s.active_projects.clone()
 }
 
+#[tauri::command]
+fn get_watcher_metrics(stats: State<WatcherStats>) -> WatcherMetrics {
+    stats.snapshot()
+}
+
 #[tauri::command]
 fn send_notification(app: tauri::AppHandle, title: &str, body: &str) -> Result<(), String> {
     use tauri_plugin_notification::NotificationExt;
@@ -42,6 +165,36 @@ fn send_notification(app: tauri::AppHandle, title: &str, body: &str) -> Result<(
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+fn get_server_info() -> Option<ServerInfo> {
+    runtime::read_server_info()
+}
+
+#[tauri::command]
+async fn say(
+    speaker: State<'_, Speaker>,
+    text: String,
+    duration: Option<u32>,
+    priority: Option<Priority>,
+    mood: Option<String>,
+) -> Result<SayOutcome, String> {
+    let request = SayRequest {
+        text,
+        duration,
+        priority: priority.unwrap_or_default(),
+        mood,
+    };
+    speaker
+        .say(request)
+        .await
+        .ok_or_else(|| "speech queue stopped".to_string())
+}
+
+#[tauri::command]
+fn get_quiet_status(quiet: State<Quiet>) -> QuietStatus {
+    quiet.status()
+}
+
 #[tauri::command]
 fn get_appearance_config() -> config::AppearanceConfig {
     let cfg = config::load_config();
@@ -63,6 +216,38 @@ fn save_appearance_config(background: Option<String>, theme: Option<String>) ->
     config::save_config(&cfg).map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+fn set_size(app: tauri::AppHandle, size: String) -> Result<(), String> {
+    let window = app
+        .get_webview_window("main")
+        .ok_or_else(|| "no main window".to_string())?;
+    window::set_size(&window, &size).map_err(|e| e.to_string())
+}
+
+/// Called by the frontend right before it starts dragging the window
+#[tauri::command]
+fn begin_drag() {
+    window::begin_drag();
+}
+
+#[tauri::command]
+fn set_window_mode(app: tauri::AppHandle, mode: WindowMode, enabled: bool) -> Result<WindowConfig, String> {
+    update_window_mode(&app, mode, enabled)
+}
+
+/// Toggle starting the app on login from the config window. Keeps an
+/// existing entry, e.g. a daemon unit, when asked to enable.
+#[tauri::command]
+fn set_auto_start(enabled: bool) -> Result<bool, String> {
+    if enabled && autostart::status().is_none() {
+        let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+        autostart::enable(autostart::Method::Desktop, &exe).map_err(|e| e.to_string())?;
+    } else if !enabled {
+        autostart::disable().map_err(|e| e.to_string())?;
+    }
+    autostart::sync_config().map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 fn remove_project(path: String) -> Result<(), String> {
     let mut cfg = config::load_config();
@@ -70,136 +255,324 @@ fn remove_project(path: String) -> Result<(), String> {
     config::save_config(&cfg).map_err(|e| e.to_string())
 }
 
+/// What a launch asks for: `claudy`, `claudy --config` or
+/// `claudy --register [dir]`. Forwarded to the running instance if there is one.
+enum LaunchIntent {
+    Show,
+    OpenConfig,
+    Register(String),
+}
+
+impl LaunchIntent {
+    fn from_args() -> Self {
+        let mut args = std::env::args().skip(1);
+        match args.next().as_deref() {
+            Some("--config") => LaunchIntent::OpenConfig,
+            Some("--register") => {
+                let dir = args.next().map(PathBuf::from).unwrap_or_else(|| ".".into());
+                let path = std::path::absolute(&dir).unwrap_or(dir);
+                LaunchIntent::Register(path.to_string_lossy().to_string())
+            }
+            _ => LaunchIntent::Show,
+        }
+    }
+
+    fn command(&self) -> Command {
+        match self {
+            LaunchIntent::Show => Command::ShowWindow,
+            LaunchIntent::OpenConfig => Command::OpenConfig,
+            LaunchIntent::Register(path) => Command::RegisterProject { path: path.clone() },
+        }
+    }
+}
+
+/// Pass a second launch's intent on to the running instance
+fn hand_off(intent: &LaunchIntent, instance: Option<InstanceInfo>) {
+    if let Some(instance) = instance.filter(|i| i.headless) {
+        if !matches!(intent, LaunchIntent::Register(_)) {
+            eprintln!(
+                "Claudy is already running without a window (pid {}). Stop it with 'claudy-cli stop' first.",
+                instance.pid
+            );
+            std::process::exit(1);
+        }
+    }
+
+    if let Err(e) = client::request(intent.command()) {
+        eprintln!("Claudy is already running, but could not be reached: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn toggle_window(app: &tauri::AppHandle) {
+    app.state::<AutoHide>().toggle();
+}
+
+fn show_window(app: &tauri::AppHandle) {
+    app.state::<AutoHide>().show();
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.set_focus();
+    }
+}
+
+fn open_config_window(app: &tauri::AppHandle) {
+    // Check if config window already exists
+    if let Some(window) = app.get_webview_window("config") {
+        let _ = window.show();
+        let _ = window.set_focus();
+        return;
+    }
+
+    // Create new config window
+    let config_url = if cfg!(debug_assertions) {
+        "http://localhost:5173/config.html"
+    } else {
+        "config.html"
+    };
+
+    if let Ok(window) = tauri::WebviewWindowBuilder::new(
+        app,
+        "config",
+        tauri::WebviewUrl::App(config_url.into())
+    )
+    .title("Claudy Configuration")
+    .inner_size(400.0, 500.0)
+    .resizable(true)
+    .center()
+    .build() {
+        let _ = window.show();
+    }
+}
+
 fn main() {
-    let shared_state: SharedState = Arc::new(Mutex::new(ClaudyState::new()));
-    let state_for_watcher = shared_state.clone();
-    let state_for_ws = shared_state.clone();
+    let intent = LaunchIntent::from_args();
+    let _lock = match InstanceLock::acquire(false) {
+        Ok(lock) => Some(lock),
+        Err(LockError::Running(instance)) => {
+            hand_off(&intent, instance);
+            return;
+        }
+        Err(LockError::Io(e)) => {
+            eprintln!("[Claudy] Could not take the instance lock: {}", e);
+            None
+        }
+    };
+    if let LaunchIntent::Register(path) = &intent {
+        if let Err(e) = config::register_project(path) {
+            eprintln!("[Claudy] Failed to register {}: {}", path, e);
+        }
+    }
 
-    // Start tokio runtime for WebSocket server
-    let ws_broadcaster: Arc<Mutex<Option<StateBroadcaster>>> = Arc::new(Mutex::new(None));
-    let ws_broadcaster_for_setup = ws_broadcaster.clone();
+    // The entry may have been added or removed outside the app
+    if let Err(e) = autostart::sync_config() {
+        eprintln!("[Claudy] Failed to sync behavior.auto_start: {}", e);
+    }
 
-    tauri::Builder::default()
+    let backend = Backend::new(&config::load_config(), tauri::async_runtime::handle().inner());
+    let speaker = backend.context.speaker.clone();
+    let quiet = backend.context.quiet.clone();
+
+    let app = tauri::Builder::default()
         .plugin(tauri_plugin_notification::init())
-        .manage(shared_state)
-        .invoke_handler(tauri::generate_handler![get_state, get_active_projects, send_notification, get_appearance_config, get_full_config, save_appearance_config, remove_project])
+        .manage(backend.state.clone())
+        .manage(PipelineSlot::default())
+        .manage(AutoHide::default())
+        .manage(quiet.clone())
+        .manage(speaker.clone())
+        .invoke_handler(tauri::generate_handler![get_state, get_active_projects, get_watcher_metrics, get_server_info, send_notification, say, get_quiet_status, get_appearance_config, get_full_config, save_appearance_config, set_size, begin_drag, set_window_mode, set_auto_start, remove_project])
         .setup(move |app| {
-            // Start WebSocket server in tokio runtime
-            let state_for_ws_clone = state_for_ws.clone();
-            let ws_broadcaster_clone = ws_broadcaster_for_setup.clone();
-            std::thread::spawn(move || {
-                let rt = tokio::runtime::Runtime::new().expect("Failed to create tokio runtime");
-                rt.block_on(async {
-                    if let Some(broadcaster) = websocket::start_server(state_for_ws_clone).await {
-                        *ws_broadcaster_clone.lock().unwrap() = Some(broadcaster);
+            let cfg = config::load_config();
+            let runtime = tauri::async_runtime::handle();
+
+            // Start the servers on the shared async runtime
+            let app_handle = app.handle().clone();
+            backend.start_servers(&cfg, runtime.inner(), move |error| {
+                let notice = Notice {
+                    project_name: String::new(),
+                    title: "Claudy server unavailable".to_string(),
+                    body: error,
+                };
+                let _ = show_notice(&app_handle, &notice, false);
+            });
+
+            // Forward speech bubbles to the frontend
+            let mut bubbles = speaker.subscribe();
+            let app_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                while bubbles.changed().await.is_ok() {
+                    let bubble = bubbles.borrow_and_update().clone();
+                    if let Err(e) = app_handle.emit("claudy-bubble", &bubble) {
+                        eprintln!("[Claudy] Emit error: {}", e);
                     }
-                    // Keep runtime alive
-                    loop {
-                        tokio::time::sleep(tokio::time::Duration::from_secs(3600)).await;
+                }
+            });
+
+            // Requests from commands, e.g. a second launch handing off
+            let mut requests = backend.context.app.subscribe();
+            let app_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                loop {
+                    match requests.recv().await {
+                        Ok(AppRequest::ShowWindow) => show_window(&app_handle),
+                        Ok(AppRequest::OpenConfig) => open_config_window(&app_handle),
+                        Ok(AppRequest::ApplyWindowModes) => apply_window_modes(&app_handle),
+                        Ok(AppRequest::ApplySize) => {
+                            if let Some(window) = app_handle.get_webview_window("main") {
+                                position_window(&window, &config::load_config());
+                            }
+                        }
+                        Ok(AppRequest::Quit) => app_handle.exit(0),
+                        Err(RecvError::Lagged(_)) => {}
+                        Err(RecvError::Closed) => break,
                     }
-                });
+                }
             });
+
             // Setup tray
             let quit = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
             let show = MenuItem::with_id(app, "show", "Show/Hide", true, None::<&str>)?;
             let config = MenuItem::with_id(app, "config", "Configuration", true, None::<&str>)?;
-            let menu = Menu::with_items(app, &[&show, &config, &quit])?;
+            let register = MenuItem::with_id(app, "register_folder", "Register Folder…", true, None::<&str>)?;
+            let icon = app.default_window_icon().unwrap().clone().to_owned();
+            let live = LiveTray::new(app.handle(), icon.clone(), backend.context.clone(), runtime.inner())?;
+            let reset = MenuItem::with_id(app, "reset_position", "Reset Position", true, None::<&str>)?;
+            let dnd = CheckMenuItem::with_id(app, "dnd", "Do Not Disturb", true, quiet.status().dnd, None::<&str>)?;
+            let cfg_window = config::load_config().window;
+            let mut mode_items = Vec::new();
+            for mode in WindowMode::ALL {
+                let item = CheckMenuItem::with_id(
+                    app,
+                    mode_item_id(mode),
+                    mode_label(mode),
+                    true,
+                    cfg_window.get(mode),
+                    None::<&str>,
+                )?;
+                mode_items.push((mode, item));
+            }
+            let separators = [
+                PredefinedMenuItem::separator(app)?,
+                PredefinedMenuItem::separator(app)?,
+                PredefinedMenuItem::separator(app)?,
+                PredefinedMenuItem::separator(app)?,
+            ];
+            let mut items: Vec<&dyn IsMenuItem<tauri::Wry>> = vec![
+                &show,
+                &config,
+                &separators[0],
+                &live.projects,
+                &live.transcript,
+                &register,
+                &separators[1],
+                &live.pause,
+                &dnd,
+                &separators[2],
+            ];
+            items.extend(mode_items.iter().map(|(_, item)| item as &dyn IsMenuItem<tauri::Wry>));
+            items.push(&reset);
+            items.push(&separators[3]);
+            items.push(&quit);
+            let menu = Menu::with_items(app, &items)?;
+            app.manage(ModeItems(mode_items));
 
-            let _tray = TrayIconBuilder::new()
-                .icon(app.default_window_icon().unwrap().clone())
+            let tray = TrayIconBuilder::new()
+                .icon(icon)
+                .tooltip("Claudy")
                 .menu(&menu)
                 .on_menu_event(|app, event| match event.id.as_ref() {
                     "quit" => app.exit(0),
-                    "show" => {
+                    "show" => toggle_window(app),
+                    "config" => open_config_window(app),
+                    "pause" => app.state::<LiveTray>().toggle_pause(),
+                    "register_folder" => app.state::<LiveTray>().register_folder(),
+                    "open_transcript" => app.state::<LiveTray>().open_transcript(),
+                    id if id.starts_with(PROJECT_ITEM_PREFIX) => app.state::<LiveTray>().focus_project(id),
+                    "dnd" => {
+                        let quiet = app.state::<Quiet>();
+                        quiet.set_dnd(!quiet.status().dnd, None);
+                    }
+                    "reset_position" => {
                         if let Some(window) = app.get_webview_window("main") {
-                            if window.is_visible().unwrap_or(false) {
-                                let _ = window.hide();
-                            } else {
-                                let _ = window.show();
-                                let _ = window.set_focus();
+                            if let Err(e) = reset_position(&window) {
+                                eprintln!("[Claudy] Failed to reset the position: {}", e);
                             }
                         }
                     }
-                    "config" => {
-                        // Check if config window already exists
-                        if let Some(window) = app.get_webview_window("config") {
-                            let _ = window.show();
-                            let _ = window.set_focus();
-                        } else {
-                            // Create new config window
-                            let config_url = if cfg!(debug_assertions) {
-                                "http://localhost:5173/config.html"
-                            } else {
-                                "config.html"
-                            };
-
-                            if let Ok(window) = tauri::WebviewWindowBuilder::new(
-                                app,
-                                "config",
-                                tauri::WebviewUrl::App(config_url.into())
-                            )
-                            .title("Claudy Configuration")
-                            .inner_size(400.0, 500.0)
-                            .resizable(true)
-                            .center()
-                            .build() {
-                                let _ = window.show();
+                    id => {
+                        if let Some(mode) = WindowMode::ALL.into_iter().find(|m| mode_item_id(*m) == id) {
+                            let enabled = !config::load_config().window.get(mode);
+                            if let Err(e) = update_window_mode(app, mode, enabled) {
+                                eprintln!("[Claudy] Failed to change the window mode: {}", e);
                             }
                         }
                     }
-                    _ => {}
                 })
                 .build(app)?;
+            live.spawn(tray);
+            app.manage(live);
+
+            // Follow quiet time in the tray and the frontend
+            let mut quiet_changes = quiet.subscribe();
+            let app_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                while quiet_changes.changed().await.is_ok() {
+                    let status = *quiet_changes.borrow_and_update();
+                    let _ = dnd.set_checked(status.dnd);
+                    if let Err(e) = app_handle.emit("claudy-quiet", status) {
+                        eprintln!("[Claudy] Emit error: {}", e);
+                    }
+                }
+            });
 
             // Position window based on config
             if let Some(window) = app.get_webview_window("main") {
                 let cfg = config::load_config();
                 position_window(&window, &cfg);
+                apply_window_modes(app.handle());
+                track_drags(&window);
+                app.state::<AutoHide>()
+                    .spawn(window.clone(), backend.context.pipeline.subscribe_config());
+                spawn_positioner(window, backend.context.pipeline.subscribe_config());
             }
 
-            // Start watcher for registered projects
-            let state_clone = state_for_watcher.clone();
-            let app_handle = app.handle().clone();
-            let ws_broadcaster_for_watcher = ws_broadcaster.clone();
-
-            std::thread::spawn(move || {
-                let watcher_result = SessionWatcher::new(move |event| {
-                    eprintln!("[Claudy] Processing event: {:?}", event);
-                    let mut s = state_clone.lock().unwrap();
-                    s.handle_event(event);
-                    let current_state = s.current_state.clone();
-                    let full_state = s.clone();
-                    drop(s); // Release lock before emitting
-
-                    // Emit event to Tauri frontend
-                    eprintln!("[Claudy] Emitting state: {}", current_state);
-                    if let Err(e) = app_handle.emit("claudy-state-change", &current_state) {
-                        eprintln!("[Claudy] Emit error: {}", e);
-                    }
+            if let LaunchIntent::OpenConfig = intent {
+                open_config_window(app.handle());
+            }
 
-                    // Broadcast to WebSocket clients
-                    if let Some(ref broadcaster) = *ws_broadcaster_for_watcher.lock().unwrap() {
-                        websocket::broadcast_state(broadcaster, &full_state);
-                    }
+            // Start the event pipeline for registered projects
+            let config_changes = backend.context.pipeline.subscribe_config();
+            let backend = backend
+                .with_sink(TauriSink(app.handle().clone()))
+                .with_sink(app.state::<AutoHide>().inner().clone())
+                .with_sink(NotificationSink {
+                    app: app.handle().clone(),
+                    rules: Mutex::default(),
+                    config: config_changes,
+                    quiet: app.state::<Quiet>().inner().clone(),
                 });
 
-                match watcher_result {
-                    Ok(mut watcher) => loop {
-                        let cfg = config::load_config();
-                        if let Err(e) = watcher.sync_projects(&cfg.projects.registered) {
-                            eprintln!("Failed to sync watched projects: {}", e);
-                        }
-
-                        std::thread::sleep(std::time::Duration::from_secs(2));
-                    },
-                    Err(e) => {
-                        eprintln!("Failed to create watcher: {}", e);
-                    }
+            match backend.spawn_pipeline(runtime.inner()) {
+                Ok(handle) => {
+                    app.manage(handle.stats());
+                    *app.state::<PipelineSlot>().lock().unwrap() = Some(handle);
                 }
-            });
+                Err(e) => {
+                    eprintln!("Failed to create watcher: {}", e);
+                }
+            }
 
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application");
+
+    app.run(|app, event| {
+        if let RunEvent::Exit = event {
+            let handle = app.state::<PipelineSlot>().lock().unwrap().take();
+            if let Some(handle) = handle {
+                tauri::async_runtime::block_on(handle.shutdown());
+            }
+            backend::cleanup();
+        }
+    });
 }
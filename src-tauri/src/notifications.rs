@@ -0,0 +1,95 @@
+//! Rules deciding when the backend raises desktop notifications on its own
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::pipeline::StateUpdate;
+use crate::watcher::{path_to_slug, ClaudeEvent};
+
+/// A notification the rules decided to raise
+#[derive(Debug, Clone)]
+pub struct Notice {
+    /// Directory name of the project, for focus checks
+    pub project_name: String,
+    pub title: String,
+    pub body: String,
+}
+
+/// Tracks turn timing per project and maps events to notices according to
+/// `[notifications]`
+#[derive(Default)]
+pub struct NotificationRules {
+    turn_started: HashMap<String, Instant>,
+}
+
+impl NotificationRules {
+    pub fn evaluate(&mut self, update: &StateUpdate, config: &Config) -> Option<Notice> {
+        let rules = &config.notifications;
+        let project = update.event.project();
+        let project_name = project_display_name(project, &config.projects.registered);
+
+        let (title, body) = match &update.event {
+            ClaudeEvent::UserMessage { .. } => {
+                self.turn_started.insert(project.to_string(), Instant::now());
+                return None;
+            }
+            ClaudeEvent::Stop { success, .. } => {
+                let elapsed = self.turn_started.remove(project)?.elapsed();
+                if rules.turn_finished_after == 0
+                    || elapsed < Duration::from_secs(rules.turn_finished_after.into())
+                {
+                    return None;
+                }
+                let verb = if *success { "finished" } else { "stopped" };
+                (
+                    format!("{}: turn {}", project_name, verb),
+                    format!("Claude {} after {}", verb, format_duration(elapsed)),
+                )
+            }
+            ClaudeEvent::Error { message, .. } if rules.on_error => {
+                (format!("{}: error", project_name), message.clone())
+            }
+            ClaudeEvent::NeedsAttention { .. } if rules.on_needs_attention => (
+                format!("{}: needs attention", project_name),
+                "Claude is waiting for your input".to_string(),
+            ),
+            ClaudeEvent::SessionStart { .. }
+                if rules.on_background_session
+                    && update.state.focused_project.as_deref() != Some(project) =>
+            {
+                (
+                    format!("{}: session started", project_name),
+                    "A Claude session started in a background project".to_string(),
+                )
+            }
+            _ => return None,
+        };
+
+        Some(Notice {
+            project_name,
+            title,
+            body,
+        })
+    }
+}
+
+/// Directory name of a registered project, falling back to the slug
+pub fn project_display_name(slug: &str, registered: &[String]) -> String {
+    registered
+        .iter()
+        .find(|path| path_to_slug(Path::new(path)) == slug)
+        .and_then(|path| Path::new(path).file_name())
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| slug.to_string())
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs < 60 {
+        format!("{}s", secs)
+    } else {
+        format!("{}m {}s", secs / 60, secs % 60)
+    }
+}
@@ -0,0 +1,293 @@
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::runtime::Handle;
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+use crate::config::{self, Config};
+use crate::state::{ClaudyState, SharedState};
+use crate::watcher::{ClaudeEvent, FsChange, SessionWatcher, TranscriptReader, WatcherStats};
+
+/// Bursty writes to the same transcript within this window are read once
+const DEBOUNCE: Duration = Duration::from_millis(75);
+
+/// Upper bound on distinct paths coalesced into a single batch
+const MAX_BATCH: usize = 256;
+
+/// Capacity of the fs change queue; notify blocks once it is full
+const FS_QUEUE: usize = 1024;
+
+const HOUSEKEEPING_INTERVAL: Duration = Duration::from_secs(10);
+
+/// A state change produced by the reducer and handed to every sink
+#[derive(Debug, Clone)]
+pub struct StateUpdate {
+    pub event: ClaudeEvent,
+    pub state: ClaudyState,
+}
+
+/// Consumer at the end of the pipeline. Called on the pipeline task for every
+/// update, so implementations must not block; hand slow work off to a task.
+pub trait Sink: Send + Sync {
+    fn handle(&self, update: &StateUpdate);
+}
+
+enum Control {
+    ReloadConfig,
+}
+
+/// Event pipeline: watch source → debounce → parser → state reducer → sinks
+pub struct Pipeline {
+    state: SharedState,
+    sinks: Vec<Box<dyn Sink>>,
+    controller: PipelineController,
+    /// Taken when the pipeline is spawned
+    control_rx: Option<mpsc::Receiver<Control>>,
+}
+
+/// Cheap, cloneable remote for a pipeline, usable before it is spawned
+#[derive(Clone)]
+pub struct PipelineController {
+    control: mpsc::Sender<Control>,
+    paused: Arc<AtomicBool>,
+    /// The config as of the last reload
+    config: Arc<watch::Sender<Arc<Config>>>,
+}
+
+/// Handle to a running pipeline
+pub struct PipelineHandle {
+    controller: PipelineController,
+    shutdown: watch::Sender<bool>,
+    task: JoinHandle<()>,
+    stats: WatcherStats,
+}
+
+impl Pipeline {
+    pub fn new(state: SharedState, config: &Config) -> Self {
+        let (control_tx, control_rx) = mpsc::channel(8);
+        Self {
+            state,
+            sinks: Vec::new(),
+            controller: PipelineController {
+                control: control_tx,
+                paused: Arc::default(),
+                config: Arc::new(watch::channel(Arc::new(config.clone())).0),
+            },
+            control_rx: Some(control_rx),
+        }
+    }
+
+    pub fn controller(&self) -> PipelineController {
+        self.controller.clone()
+    }
+
+    pub fn with_sink(mut self, sink: impl Sink + 'static) -> Self {
+        self.sinks.push(Box::new(sink));
+        self
+    }
+
+    pub fn with_sinks(mut self, sinks: impl IntoIterator<Item = Box<dyn Sink>>) -> Self {
+        self.sinks.extend(sinks);
+        self
+    }
+
+    /// Start the watch source and spawn the pipeline task on `runtime`
+    pub fn spawn(mut self, runtime: &Handle) -> Result<PipelineHandle, notify::Error> {
+        let (fs_tx, fs_rx) = mpsc::channel(FS_QUEUE);
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let controller = self.controller();
+        let control_rx = self.control_rx.take().expect("pipeline spawned once");
+
+        let watcher = SessionWatcher::new(fs_tx)?;
+        let stats = watcher.stats();
+
+        // Config changes drive the set of watched projects
+        let config_watcher = watch_config(controller.control.clone())
+            .map_err(|e| eprintln!("[Claudy] Config changes won't be picked up: {}", e))
+            .ok();
+
+        let task = runtime.spawn(self.run(watcher, config_watcher, fs_rx, control_rx, shutdown_rx));
+
+        // Initial project sync
+        controller.reload_config();
+
+        Ok(PipelineHandle {
+            controller,
+            shutdown: shutdown_tx,
+            task,
+            stats,
+        })
+    }
+
+    async fn run(
+        self,
+        mut watcher: SessionWatcher,
+        // Kept alive for as long as the pipeline runs
+        _config_watcher: Option<RecommendedWatcher>,
+        mut fs_rx: mpsc::Receiver<FsChange>,
+        mut control_rx: mpsc::Receiver<Control>,
+        mut shutdown_rx: watch::Receiver<bool>,
+    ) {
+        let reader = watcher.reader();
+        let mut housekeeping = tokio::time::interval(HOUSEKEEPING_INTERVAL);
+
+        loop {
+            tokio::select! {
+                _ = shutdown_rx.changed() => break,
+                Some(control) = control_rx.recv() => match control {
+                    Control::ReloadConfig => {
+                        let cfg = Arc::new(config::load_config());
+                        self.controller.config.send_replace(cfg.clone());
+                        match watcher.sync_projects(&cfg.projects.registered) {
+                            Ok(backfilled) => self.reduce(backfilled),
+                            Err(e) => eprintln!("Failed to sync watched projects: {}", e),
+                        }
+                    }
+                },
+                Some(first) = fs_rx.recv() => {
+                    let batch = collect_batch(first, &mut fs_rx).await;
+                    let events = read_batch(&reader, batch).await;
+                    self.reduce(events);
+                }
+                _ = housekeeping.tick() => watcher.housekeeping(),
+            }
+        }
+
+        eprintln!("[Claudy] Event pipeline stopped");
+    }
+
+    /// Apply events to the shared state and fan the results out to the sinks
+    fn reduce(&self, events: Vec<ClaudeEvent>) {
+        if self.controller.is_paused() {
+            return;
+        }
+
+        for event in events {
+            eprintln!("[Claudy] Processing event: {:?}", event);
+            let state = {
+                let mut s = self.state.lock().unwrap();
+                s.handle_event(event.clone());
+                s.clone()
+            }; // Release lock before notifying sinks
+
+            let update = StateUpdate { event, state };
+            for sink in &self.sinks {
+                sink.handle(&update);
+            }
+        }
+    }
+}
+
+impl PipelineController {
+    /// Re-read the config and resync watched projects
+    pub fn reload_config(&self) {
+        // A full queue means a reload is already pending
+        let _ = self.control.try_send(Control::ReloadConfig);
+    }
+
+    /// The config as of the last reload, so nothing has to read the file on
+    /// every use
+    pub fn config(&self) -> Arc<Config> {
+        self.config.borrow().clone()
+    }
+
+    /// Receiver notified whenever the config is reloaded, e.g. after the file
+    /// changed
+    pub fn subscribe_config(&self) -> watch::Receiver<Arc<Config>> {
+        self.config.subscribe()
+    }
+
+    /// While paused, transcripts are still followed but their events are
+    /// dropped instead of changing the state
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+}
+
+impl PipelineHandle {
+    pub fn stats(&self) -> WatcherStats {
+        self.stats.clone()
+    }
+
+    pub fn controller(&self) -> PipelineController {
+        self.controller.clone()
+    }
+
+    /// Stop the pipeline and wait for in-flight events to be delivered
+    pub async fn shutdown(self) {
+        let _ = self.shutdown.send(true);
+        let _ = self.task.await;
+    }
+}
+
+/// Gather changes arriving within the debounce window, keeping only the
+/// latest change per path.
+async fn collect_batch(first: FsChange, rx: &mut mpsc::Receiver<FsChange>) -> Vec<FsChange> {
+    let deadline = Instant::now() + DEBOUNCE;
+    let mut order: Vec<PathBuf> = Vec::new();
+    let mut batch: HashMap<PathBuf, FsChange> = HashMap::new();
+
+    let mut next = Some(first);
+    while let Some(change) = next.take() {
+        let path = match &change {
+            FsChange::Modified(path) | FsChange::Removed(path) => path.clone(),
+        };
+        if batch.insert(path.clone(), change).is_none() {
+            order.push(path);
+        }
+
+        if order.len() >= MAX_BATCH {
+            break;
+        }
+        if let Ok(change) = tokio::time::timeout_at(deadline, rx.recv()).await {
+            next = change;
+        }
+    }
+
+    order
+        .into_iter()
+        .filter_map(|path| batch.remove(&path))
+        .collect()
+}
+
+async fn read_batch(reader: &TranscriptReader, batch: Vec<FsChange>) -> Vec<ClaudeEvent> {
+    let reader = reader.clone();
+    match tokio::task::spawn_blocking(move || reader.read_changes(&batch)).await {
+        Ok(events) => events,
+        Err(e) => {
+            eprintln!("[Claudy] Transcript reader failed: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Watch the config file's directory and request a reload whenever it changes
+fn watch_config(control: mpsc::Sender<Control>) -> Result<RecommendedWatcher, notify::Error> {
+    let path = config::config_path();
+    let dir = path
+        .parent()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("."));
+    std::fs::create_dir_all(&dir)?;
+
+    let mut watcher = notify::recommended_watcher(move |res: Result<notify::Event, notify::Error>| {
+        if let Ok(event) = res {
+            if event.paths.iter().any(|p| p == &path) {
+                // A full queue means a reload is already pending
+                let _ = control.try_send(Control::ReloadConfig);
+            }
+        }
+    })?;
+    watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+
+    Ok(watcher)
+}
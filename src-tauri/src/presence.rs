@@ -0,0 +1,126 @@
+//! Registry of connected clients, so users can see which overlays and
+//! dashboards are attached and disconnect misbehaving ones
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+
+use crate::auth::Scope;
+use crate::history::now_millis;
+use crate::protocol::Subscription;
+
+/// Role the CLI declares for its one-shot requests, which aren't listed
+pub const CLI_ROLE: &str = "cli";
+
+/// A connected client as reported by `list_clients`
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ClientInfo {
+    pub id: u64,
+    pub addr: String,
+    /// Declared with `hello`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Declared with `hello`, e.g. `overlay` or `dashboard`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    pub scope: Scope,
+    /// Milliseconds since the Unix epoch
+    pub connected_since: u64,
+    pub subscription: Subscription,
+    /// Broadcasts the client missed because it fell behind
+    pub lagged: u64,
+}
+
+struct Client {
+    info: ClientInfo,
+    kick: Option<oneshot::Sender<()>>,
+}
+
+#[derive(Default)]
+struct Registry {
+    next_id: u64,
+    clients: BTreeMap<u64, Client>,
+}
+
+/// Shared registry of connected clients
+#[derive(Clone, Default)]
+pub struct ClientRegistry(Arc<Mutex<Registry>>);
+
+/// Registration of one connection; removes the client when dropped
+pub struct ClientGuard {
+    pub id: u64,
+    /// Fires when the client is kicked
+    pub kicked: oneshot::Receiver<()>,
+    registry: ClientRegistry,
+}
+
+impl ClientRegistry {
+    pub fn register(&self, addr: impl ToString, scope: Scope) -> ClientGuard {
+        let (kick_tx, kick_rx) = oneshot::channel();
+        let mut registry = self.0.lock().unwrap();
+        registry.next_id += 1;
+        let id = registry.next_id;
+
+        let info = ClientInfo {
+            id,
+            addr: addr.to_string(),
+            name: None,
+            role: None,
+            scope,
+            connected_since: now_millis(),
+            subscription: Subscription::default(),
+            lagged: 0,
+        };
+        registry.clients.insert(
+            id,
+            Client {
+                info,
+                kick: Some(kick_tx),
+            },
+        );
+
+        ClientGuard {
+            id,
+            kicked: kick_rx,
+            registry: self.clone(),
+        }
+    }
+
+    /// Update a client's entry in place
+    pub fn update(&self, id: u64, f: impl FnOnce(&mut ClientInfo)) {
+        if let Some(client) = self.0.lock().unwrap().clients.get_mut(&id) {
+            f(&mut client.info);
+        }
+    }
+
+    /// Connected clients, leaving out the CLI's own requests
+    pub fn list(&self) -> Vec<ClientInfo> {
+        let registry = self.0.lock().unwrap();
+        registry
+            .clients
+            .values()
+            .filter(|c| c.info.role.as_deref() != Some(CLI_ROLE))
+            .map(|c| c.info.clone())
+            .collect()
+    }
+
+    /// Ask a client's connection to close; false if there is no such client
+    pub fn kick(&self, id: u64) -> bool {
+        let mut registry = self.0.lock().unwrap();
+        match registry.clients.get_mut(&id).and_then(|c| c.kick.take()) {
+            Some(kick) => {
+                let _ = kick.send(());
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Drop for ClientGuard {
+    fn drop(&mut self) {
+        self.registry.0.lock().unwrap().clients.remove(&self.id);
+    }
+}
@@ -0,0 +1,324 @@
+//! Versioned WebSocket protocol. Every message is a JSON envelope:
+//! `{"v": 1, "type": "state" | "event" | "command" | "response" | "error", "id": ...}`.
+//! Clients send `command` messages; `id` is echoed on the matching
+//! `response` or `error`.
+
+use schemars::{JsonSchema, Schema};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+
+use crate::config::WindowMode;
+use crate::speech::SayRequest;
+use crate::state::ClaudyState;
+use crate::watcher::{path_to_slug, ClaudeEvent};
+
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Message sent by a client
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ClientMessage {
+    pub v: u32,
+    #[serde(rename = "type")]
+    pub kind: ClientMessageKind,
+    /// Correlation id echoed in the reply
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Value>,
+    #[serde(flatten)]
+    pub command: Command,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ClientMessageKind {
+    Command,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "command", content = "params", rename_all = "snake_case")]
+pub enum Command {
+    /// Force the mascot into a state
+    SetState {
+        state: String,
+        #[serde(default)]
+        suppress_comments: bool,
+    },
+    /// Show a speech bubble, queued behind the current one
+    Say(SayRequest),
+    /// Make an active project the focused one
+    FocusProject { project: String },
+    /// Choose which broadcasts this connection receives
+    Subscribe(Subscription),
+    /// Tell the server who this client is, shown by `list_clients`
+    Hello {
+        #[serde(default)]
+        name: Option<String>,
+        #[serde(default)]
+        role: Option<String>,
+    },
+    /// Ask for a fresh `state`, e.g. after noticing a `seq` gap
+    Resync,
+    /// App, server and pipeline status
+    Status,
+    /// Stop (or resume) reacting to transcripts
+    Pause { paused: bool },
+    /// Re-read the config file
+    Reload,
+    /// Connected clients
+    ListClients,
+    /// Disconnect a client by id
+    KickClient { id: u64 },
+    /// Recent events, oldest first
+    GetHistory {
+        #[serde(default)]
+        limit: Option<usize>,
+    },
+    GetConfig,
+    /// Add a project directory to the config and start watching it
+    RegisterProject { path: String },
+    /// Show and focus the mascot window (ignored by the daemon)
+    ShowWindow,
+    /// Open the configuration window (ignored by the daemon)
+    OpenConfig,
+    /// Stop the running instance
+    Shutdown,
+    /// Resize the mascot: "small", "medium", "large" or a scale factor
+    SetSize { size: String },
+    /// Turn one of the `[window]` toggles on or off
+    SetWindowMode { mode: WindowMode, enabled: bool },
+    /// Turn Do-Not-Disturb on or off
+    SetDnd {
+        enabled: bool,
+        /// Seconds until DND turns itself off
+        #[serde(default)]
+        duration: Option<u64>,
+    },
+}
+
+impl Command {
+    /// Whether the command needs the control scope: it changes state, or
+    /// reveals the config or the connected clients
+    pub fn is_control(&self) -> bool {
+        matches!(
+            self,
+            Command::SetState { .. }
+                | Command::Say(_)
+                | Command::FocusProject { .. }
+                | Command::GetConfig
+                | Command::Pause { .. }
+                | Command::Reload
+                | Command::ListClients
+                | Command::KickClient { .. }
+                | Command::RegisterProject { .. }
+                | Command::ShowWindow
+                | Command::OpenConfig
+                | Command::Shutdown
+                | Command::SetSize { .. }
+                | Command::SetWindowMode { .. }
+                | Command::SetDnd { .. }
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Subscription {
+    /// Receive `state` snapshots (on by default)
+    #[serde(default = "default_true")]
+    pub state: bool,
+    /// Receive `event` messages
+    #[serde(default)]
+    pub events: bool,
+    /// Only events of these projects (slugs or paths); all when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub projects: Vec<String>,
+    /// Only events of these session ids; all when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sessions: Vec<String>,
+    /// Only these event kinds, e.g. `ToolUse`; all when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub kinds: Vec<String>,
+}
+
+impl Default for Subscription {
+    fn default() -> Self {
+        Self {
+            state: true,
+            events: false,
+            projects: Vec::new(),
+            sessions: Vec::new(),
+            kinds: Vec::new(),
+        }
+    }
+}
+
+impl Subscription {
+    /// Whether an event passes this subscription's filters
+    pub fn wants_event(&self, event: &ClaudeEvent) -> bool {
+        let project = event.project();
+        self.events
+            && (self.projects.is_empty()
+                || self
+                    .projects
+                    .iter()
+                    .any(|p| p == project || path_to_slug(Path::new(p)) == project))
+            && (self.sessions.is_empty() || self.sessions.iter().any(|s| s == event.session()))
+            && (self.kinds.is_empty() || self.kinds.iter().any(|k| k == event.kind()))
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Message sent by the server
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ServerMessage {
+    pub v: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Value>,
+    /// Per-connection sequence number. A jump means broadcasts were dropped
+    /// for a slow client, which then gets a fresh `state`.
+    #[serde(default)]
+    pub seq: u64,
+    #[serde(flatten)]
+    pub body: ServerBody,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerBody {
+    State { state: ClaudyState },
+    Event { event: ClaudeEvent },
+    Response { result: Value },
+    Error { code: ErrorCode, message: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// Not valid JSON
+    ParseError,
+    UnsupportedVersion,
+    /// Valid JSON but not a valid envelope or command
+    InvalidMessage,
+    UnknownCommand,
+    /// The client's scope doesn't allow the command
+    Forbidden,
+    NotFound,
+    Internal,
+}
+
+#[derive(Debug, Clone)]
+pub struct ProtocolError {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl ProtocolError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl ServerMessage {
+    pub fn new(body: ServerBody) -> Self {
+        Self {
+            v: PROTOCOL_VERSION,
+            id: None,
+            seq: 0,
+            body,
+        }
+    }
+
+    pub fn state(state: ClaudyState) -> Self {
+        Self::new(ServerBody::State { state })
+    }
+
+    pub fn event(event: ClaudeEvent) -> Self {
+        Self::new(ServerBody::Event { event })
+    }
+
+    pub fn response(id: Option<Value>, result: Value) -> Self {
+        Self {
+            id,
+            ..Self::new(ServerBody::Response { result })
+        }
+    }
+
+    pub fn error(id: Option<Value>, error: ProtocolError) -> Self {
+        Self {
+            id,
+            ..Self::new(ServerBody::Error {
+                code: error.code,
+                message: error.message,
+            })
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_else(|_| "{}".to_string())
+    }
+}
+
+impl ClientMessage {
+    pub fn command(id: Option<Value>, command: Command) -> Self {
+        Self {
+            v: PROTOCOL_VERSION,
+            kind: ClientMessageKind::Command,
+            id,
+            command,
+        }
+    }
+}
+
+/// Parse a client message, keeping the id (when readable) for error replies
+pub fn parse_client_message(text: &str) -> Result<(Option<Value>, Command), (Option<Value>, ProtocolError)> {
+    let value: Value = serde_json::from_str(text)
+        .map_err(|e| (None, ProtocolError::new(ErrorCode::ParseError, e.to_string())))?;
+
+    let id = value.get("id").cloned();
+    let fail = |code, message: String| (id.clone(), ProtocolError::new(code, message));
+
+    match value.get("v").and_then(Value::as_u64) {
+        Some(v) if v == u64::from(PROTOCOL_VERSION) => {}
+        Some(v) => {
+            return Err(fail(
+                ErrorCode::UnsupportedVersion,
+                format!("unsupported protocol version {}, expected {}", v, PROTOCOL_VERSION),
+            ))
+        }
+        None => return Err(fail(ErrorCode::InvalidMessage, "missing protocol version \"v\"".into())),
+    }
+
+    if value.get("type").and_then(Value::as_str) != Some("command") {
+        return Err(fail(
+            ErrorCode::InvalidMessage,
+            "clients may only send messages of type \"command\"".into(),
+        ));
+    }
+
+    let command = serde_json::from_value::<Command>(value.clone()).map_err(|e| {
+        let message = e.to_string();
+        let code = if message.contains("unknown variant") {
+            ErrorCode::UnknownCommand
+        } else {
+            ErrorCode::InvalidMessage
+        };
+        fail(code, message)
+    })?;
+
+    Ok((id, command))
+}
+
+/// JSON Schema of the messages clients send
+pub fn client_schema() -> Schema {
+    schemars::schema_for!(ClientMessage)
+}
+
+/// JSON Schema of the messages the server sends
+pub fn server_schema() -> Schema {
+    schemars::schema_for!(ServerMessage)
+}
@@ -0,0 +1,202 @@
+//! Quiet hours and Do-Not-Disturb. While quiet, notifications and speech
+//! bubbles are suppressed; events are still tracked, so the state is right
+//! when it ends.
+
+use chrono::{DateTime, Datelike, Local, NaiveTime, Weekday};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::runtime::Handle;
+use tokio::sync::watch;
+
+use crate::config::{Config, QuietConfig, QuietSchedule};
+use crate::history::now_millis;
+
+/// How often schedules and the DND expiry are re-evaluated
+const CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct QuietStatus {
+    /// Quiet right now, from DND or a schedule
+    pub quiet: bool,
+    /// Do-Not-Disturb is on
+    pub dnd: bool,
+    /// When DND turns itself off, in milliseconds since the Unix epoch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dnd_until: Option<u64>,
+    /// Whether the mascot should stop animating while quiet
+    pub freeze_animations: bool,
+}
+
+#[derive(Clone, Copy)]
+enum Dnd {
+    Off,
+    /// Until the given time, or until turned off
+    On(Option<u64>),
+}
+
+/// Shared quiet state, re-evaluated on a timer and on config reloads
+#[derive(Clone)]
+pub struct Quiet {
+    dnd: Arc<Mutex<Dnd>>,
+    /// `[quiet]` as last loaded
+    config: Arc<Mutex<QuietConfig>>,
+    status: Arc<watch::Sender<QuietStatus>>,
+}
+
+impl Quiet {
+    pub fn spawn(runtime: &Handle, mut config: watch::Receiver<Arc<Config>>) -> Self {
+        let quiet = Self {
+            dnd: Arc::new(Mutex::new(Dnd::Off)),
+            config: Arc::new(Mutex::new(config.borrow_and_update().quiet.clone())),
+            status: Arc::new(watch::channel(QuietStatus::default()).0),
+        };
+        quiet.refresh();
+
+        let ticker = quiet.clone();
+        runtime.spawn(async move {
+            let mut interval = tokio::time::interval(CHECK_INTERVAL);
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    changed = config.changed() => {
+                        if changed.is_err() {
+                            break;
+                        }
+                        *ticker.config.lock().unwrap() = config.borrow_and_update().quiet.clone();
+                    }
+                }
+                ticker.refresh();
+            }
+        });
+        quiet
+    }
+
+    /// Turn DND on, optionally for a while, or off
+    pub fn set_dnd(&self, enabled: bool, duration: Option<Duration>) -> QuietStatus {
+        *self.dnd.lock().unwrap() = if enabled {
+            Dnd::On(duration.map(|d| now_millis().saturating_add(d.as_millis().try_into().unwrap_or(u64::MAX))))
+        } else {
+            Dnd::Off
+        };
+        self.refresh()
+    }
+
+    pub fn status(&self) -> QuietStatus {
+        *self.status.borrow()
+    }
+
+    pub fn is_quiet(&self) -> bool {
+        self.status.borrow().quiet
+    }
+
+    /// Receiver notified whenever the status changes
+    pub fn subscribe(&self) -> watch::Receiver<QuietStatus> {
+        self.status.subscribe()
+    }
+
+    fn refresh(&self) -> QuietStatus {
+        let config = self.config.lock().unwrap().clone();
+        let (dnd, dnd_until) = {
+            let mut dnd = self.dnd.lock().unwrap();
+            if let Dnd::On(Some(until)) = *dnd {
+                if now_millis() >= until {
+                    *dnd = Dnd::Off;
+                }
+            }
+            match *dnd {
+                Dnd::Off => (false, None),
+                Dnd::On(until) => (true, until),
+            }
+        };
+
+        let status = QuietStatus {
+            quiet: dnd || in_quiet_hours(&config, Local::now()),
+            dnd,
+            dnd_until,
+            freeze_animations: config.freeze_animations,
+        };
+        self.status.send_if_modified(|current| {
+            let changed = *current != status;
+            *current = status;
+            changed
+        });
+        status
+    }
+}
+
+/// Whether any schedule covers the given time
+pub fn in_quiet_hours(quiet: &QuietConfig, now: DateTime<Local>) -> bool {
+    quiet.schedules.iter().any(|s| in_schedule(s, now))
+}
+
+/// Schedules crossing midnight belong to the day they start on, so
+/// weekdays 18:00-09:00 covers Friday night but not Sunday night
+fn in_schedule(schedule: &QuietSchedule, now: DateTime<Local>) -> bool {
+    let (Some(from), Some(to)) = (parse_time(&schedule.from), parse_time(&schedule.to)) else {
+        return false;
+    };
+    let time = now.time();
+    let today = now.weekday();
+
+    if from <= to {
+        on_day(schedule, today) && from <= time && time < to
+    } else {
+        (on_day(schedule, today) && time >= from) || (on_day(schedule, today.pred()) && time < to)
+    }
+}
+
+fn on_day(schedule: &QuietSchedule, day: Weekday) -> bool {
+    schedule.days.is_empty()
+        || schedule.days.iter().any(|d| match d.to_lowercase().as_str() {
+            "weekdays" => !matches!(day, Weekday::Sat | Weekday::Sun),
+            "weekends" => matches!(day, Weekday::Sat | Weekday::Sun),
+            name => name.parse::<Weekday>().is_ok_and(|d| d == day),
+        })
+}
+
+fn parse_time(value: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(value.trim(), "%H:%M").ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn schedule(days: &[&str], from: &str, to: &str) -> QuietSchedule {
+        QuietSchedule {
+            days: days.iter().map(|d| d.to_string()).collect(),
+            from: from.to_string(),
+            to: to.to_string(),
+        }
+    }
+
+    /// A time in June 2024, which starts on a Saturday
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 6, day, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn schedules_cross_midnight() {
+        let night = schedule(&[], "22:00", "07:00");
+        assert!(in_schedule(&night, at(3, 23, 30)));
+        assert!(in_schedule(&night, at(4, 6, 59)));
+        assert!(!in_schedule(&night, at(4, 7, 0)));
+        assert!(!in_schedule(&night, at(4, 21, 59)));
+    }
+
+    #[test]
+    fn weekdays_cover_friday_night_but_not_sunday_night() {
+        let evenings = schedule(&["weekdays"], "18:00", "09:00");
+        // Friday the 7th into Saturday morning
+        assert!(in_schedule(&evenings, at(7, 23, 0)));
+        assert!(in_schedule(&evenings, at(8, 8, 0)));
+        assert!(!in_schedule(&evenings, at(8, 23, 0)));
+        // Sunday the 9th into Monday morning
+        assert!(!in_schedule(&evenings, at(9, 23, 0)));
+        assert!(!in_schedule(&evenings, at(10, 8, 0)));
+        assert!(in_schedule(&evenings, at(10, 18, 0)));
+    }
+}
@@ -0,0 +1,407 @@
+//! Plain HTTP endpoints served next to the WebSocket, on the same port, for
+//! tools that can't speak WebSocket:
+//!
+//! - `GET /health`
+//! - `GET /state`
+//! - `GET /projects`
+//! - `GET /events`, a Server-Sent Events stream of protocol messages
+//! - `POST /say`, taking the `say` command's params as JSON
+
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::broadcast;
+use tokio::time::Instant;
+use tokio_tungstenite::tungstenite::http::StatusCode;
+
+use crate::auth::{self, AuthPolicy, Scope};
+use crate::commands::ServerContext;
+use crate::protocol::{
+    Command, ErrorCode, ProtocolError, ServerBody, ServerMessage, Subscription, PROTOCOL_VERSION,
+};
+use crate::speech::SayRequest;
+use crate::websocket::{render, snapshot};
+
+const MAX_HEAD_BYTES: usize = 16 * 1024;
+const MAX_BODY_BYTES: usize = 64 * 1024;
+
+/// Clients get this long to send their request head
+const HEAD_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Comment lines keep idle event streams from being closed by proxies
+const SSE_KEEPALIVE: Duration = Duration::from_secs(20);
+
+struct HttpRequest {
+    method: String,
+    path: String,
+    query: Option<String>,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl HttpRequest {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Look at the request head without consuming it, to tell WebSocket
+/// upgrades from plain HTTP requests
+pub async fn is_websocket_upgrade(stream: &TcpStream) -> bool {
+    let mut buf = vec![0u8; MAX_HEAD_BYTES];
+    let deadline = Instant::now() + HEAD_TIMEOUT;
+
+    loop {
+        let n = match tokio::time::timeout_at(deadline, stream.peek(&mut buf)).await {
+            Ok(Ok(n)) if n > 0 => n,
+            _ => return false,
+        };
+
+        let mut headers = [httparse::EMPTY_HEADER; 64];
+        let mut request = httparse::Request::new(&mut headers);
+        match request.parse(&buf[..n]) {
+            Ok(httparse::Status::Complete(_)) => {
+                return request.headers.iter().any(|h| {
+                    h.name.eq_ignore_ascii_case("upgrade")
+                        && String::from_utf8_lossy(h.value).eq_ignore_ascii_case("websocket")
+                });
+            }
+            // Peeking returns what has arrived so far; wait for the rest
+            Ok(httparse::Status::Partial) if n < buf.len() => {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            _ => return false,
+        }
+    }
+}
+
+pub async fn handle_request(
+    mut stream: TcpStream,
+    addr: SocketAddr,
+    ctx: ServerContext,
+    policy: Arc<AuthPolicy>,
+) {
+    let request = match tokio::time::timeout(HEAD_TIMEOUT, read_request(&mut stream)).await {
+        Ok(Some(request)) => request,
+        _ => {
+            let _ = write_json(&mut stream, 400, None, &error_body("bad request")).await;
+            return;
+        }
+    };
+    eprintln!(
+        "[Claudy HTTP] {} {} from {}",
+        request.method, request.path, addr
+    );
+
+    let origin = request.header("origin").map(str::to_string);
+    let cors = origin
+        .as_deref()
+        .filter(|o| policy.origin_allowed(o))
+        .map(str::to_string);
+
+    if request.method == "OPTIONS" {
+        let _ = write_preflight(&mut stream, cors.as_deref()).await;
+        return;
+    }
+
+    if request.method == "GET" && request.path == "/health" {
+        let body = json!({ "status": "ok", "protocol": PROTOCOL_VERSION });
+        let _ = write_json(&mut stream, 200, cors.as_deref(), &body).await;
+        return;
+    }
+
+    let token = auth::request_token(request.query.as_deref(), |name| request.header(name));
+    let scope = match policy.authorize(origin.as_deref(), token) {
+        Ok(scope) => scope,
+        Err(e) => {
+            eprintln!("[Claudy HTTP] Rejected {}: {}", addr, e.message());
+            let _ = write_json(
+                &mut stream,
+                e.status(),
+                cors.as_deref(),
+                &error_body(e.message()),
+            )
+            .await;
+            return;
+        }
+    };
+
+    let result = match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/state") => {
+            let state = ctx.state.lock().unwrap().clone();
+            Ok(json!(state))
+        }
+        ("GET", "/projects") => {
+            let state = ctx.state.lock().unwrap();
+            Ok(json!({
+                "active": state.active_projects,
+                "focused": state.focused_project,
+            }))
+        }
+        ("GET", "/events") => {
+            let subscription = subscription_from_query(request.query.as_deref());
+            stream_events(stream, addr, ctx, scope, subscription, cors).await;
+            return;
+        }
+        ("POST", "/say") => match serde_json::from_slice::<SayRequest>(&request.body) {
+            Ok(say) => ctx.execute(Command::Say(say), scope).await,
+            Err(e) => Err(ProtocolError::new(ErrorCode::InvalidMessage, e.to_string())),
+        },
+        (_, "/state" | "/projects" | "/events" | "/say" | "/health") => {
+            let _ = write_json(
+                &mut stream,
+                405,
+                cors.as_deref(),
+                &error_body("method not allowed"),
+            )
+            .await;
+            return;
+        }
+        _ => {
+            let _ = write_json(&mut stream, 404, cors.as_deref(), &error_body("not found")).await;
+            return;
+        }
+    };
+
+    let (status, body) = match result {
+        Ok(value) => (200, value),
+        Err(e) => (
+            error_status(e.code),
+            json!({ "error": { "code": e.code, "message": e.message } }),
+        ),
+    };
+    let _ = write_json(&mut stream, status, cors.as_deref(), &body).await;
+}
+
+async fn read_request(stream: &mut TcpStream) -> Option<HttpRequest> {
+    let mut buf = Vec::with_capacity(1024);
+    let mut chunk = [0u8; 4096];
+
+    let head_len = loop {
+        let n = stream.read(&mut chunk).await.ok()?;
+        if n == 0 {
+            return None;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+        if buf.len() > MAX_HEAD_BYTES {
+            return None;
+        }
+    };
+
+    let mut headers = [httparse::EMPTY_HEADER; 64];
+    let mut parsed = httparse::Request::new(&mut headers);
+    parsed.parse(&buf[..head_len]).ok()?;
+
+    let target = parsed.path?;
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path.to_string(), Some(query.to_string())),
+        None => (target.to_string(), None),
+    };
+    let headers: Vec<(String, String)> = parsed
+        .headers
+        .iter()
+        .map(|h| {
+            (
+                h.name.to_string(),
+                String::from_utf8_lossy(h.value).into_owned(),
+            )
+        })
+        .collect();
+    let method = parsed.method?.to_string();
+
+    let content_length = headers
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, v)| v.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+    if content_length > MAX_BODY_BYTES {
+        return None;
+    }
+
+    let mut body = buf.split_off(head_len);
+    while body.len() < content_length {
+        let n = stream.read(&mut chunk).await.ok()?;
+        if n == 0 {
+            return None;
+        }
+        body.extend_from_slice(&chunk[..n]);
+    }
+    body.truncate(content_length);
+
+    Some(HttpRequest {
+        method,
+        path,
+        query,
+        headers,
+        body,
+    })
+}
+
+/// `/events` filters: `state=false`, `events=false`, and comma separated
+/// `projects`, `sessions` and `kinds`
+fn subscription_from_query(query: Option<&str>) -> Subscription {
+    let mut subscription = Subscription {
+        events: true,
+        ..Subscription::default()
+    };
+
+    for (key, value) in form_urlencoded::parse(query.unwrap_or_default().as_bytes()) {
+        let list = value
+            .split(',')
+            .filter(|v| !v.is_empty())
+            .map(str::to_string);
+        match key.as_ref() {
+            "state" => subscription.state = value != "false",
+            "events" => subscription.events = value != "false",
+            "projects" => subscription.projects.extend(list),
+            "sessions" => subscription.sessions.extend(list),
+            "kinds" => subscription.kinds.extend(list),
+            _ => {}
+        }
+    }
+
+    subscription
+}
+
+/// Serve protocol messages as Server-Sent Events until the client goes away
+async fn stream_events(
+    mut stream: TcpStream,
+    addr: SocketAddr,
+    ctx: ServerContext,
+    scope: Scope,
+    subscription: Subscription,
+    cors: Option<String>,
+) {
+    let mut rx = ctx.broadcaster.subscribe();
+
+    let head = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\n{}\r\n",
+        cors_headers(cors.as_deref())
+    );
+    if stream.write_all(head.as_bytes()).await.is_err() {
+        return;
+    }
+
+    let mut client = ctx.clients.register(addr, scope);
+    ctx.clients.update(client.id, |c| {
+        c.role = Some("sse".to_string());
+        c.subscription = subscription.clone();
+    });
+
+    let mut seq = 0;
+    let mut next = Some(snapshot(&ctx));
+    let mut keepalive = tokio::time::interval(SSE_KEEPALIVE);
+    keepalive.tick().await;
+
+    loop {
+        if let Some(mut message) = next.take() {
+            seq += 1;
+            message.seq = seq;
+            let frame = format!(
+                "id: {}\nevent: {}\ndata: {}\n\n",
+                seq,
+                message_type(&message),
+                message.to_json()
+            );
+            if stream.write_all(frame.as_bytes()).await.is_err() {
+                break;
+            }
+        }
+
+        next = tokio::select! {
+            outgoing = rx.recv() => match outgoing {
+                Ok(outgoing) => render(&subscription, outgoing),
+                Err(broadcast::error::RecvError::Closed) => break,
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    ctx.clients.update(client.id, |c| c.lagged += n);
+                    seq += n;
+                    Some(snapshot(&ctx))
+                }
+            },
+            _ = keepalive.tick() => {
+                if stream.write_all(b": keepalive\n\n").await.is_err() {
+                    break;
+                }
+                None
+            }
+            _ = &mut client.kicked => {
+                eprintln!("[Claudy HTTP] Kicked event stream {}", addr);
+                break;
+            }
+        };
+    }
+
+    eprintln!("[Claudy HTTP] Event stream closed for {}", addr);
+}
+
+fn message_type(message: &ServerMessage) -> &'static str {
+    match message.body {
+        ServerBody::State { .. } => "state",
+        ServerBody::Event { .. } => "event",
+        ServerBody::Response { .. } => "response",
+        ServerBody::Error { .. } => "error",
+    }
+}
+
+fn error_status(code: ErrorCode) -> u16 {
+    match code {
+        ErrorCode::Forbidden => 403,
+        ErrorCode::NotFound => 404,
+        ErrorCode::Internal => 500,
+        _ => 400,
+    }
+}
+
+fn error_body(message: &str) -> Value {
+    json!({ "error": { "message": message } })
+}
+
+fn cors_headers(origin: Option<&str>) -> String {
+    match origin {
+        Some(origin) => format!(
+            "Access-Control-Allow-Origin: {}\r\nVary: Origin\r\n",
+            origin
+        ),
+        None => String::new(),
+    }
+}
+
+async fn write_preflight(stream: &mut TcpStream, origin: Option<&str>) -> std::io::Result<()> {
+    let response = format!(
+        "HTTP/1.1 204 No Content\r\n{}Access-Control-Allow-Methods: GET, POST, OPTIONS\r\nAccess-Control-Allow-Headers: Authorization, Content-Type, X-Claudy-Token\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        cors_headers(origin)
+    );
+    stream.write_all(response.as_bytes()).await
+}
+
+async fn write_json(
+    stream: &mut TcpStream,
+    status: u16,
+    origin: Option<&str>,
+    body: &Value,
+) -> std::io::Result<()> {
+    let body = body.to_string();
+    let reason = StatusCode::from_u16(status)
+        .ok()
+        .and_then(|s| s.canonical_reason())
+        .unwrap_or("");
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        cors_headers(origin),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
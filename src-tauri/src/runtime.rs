@@ -0,0 +1,182 @@
+//! Per-user runtime directory holding files that describe the running app,
+//! so the CLI and other local tools can find it.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+const SERVER_INFO_FILE: &str = "server.json";
+const CONTROL_TOKEN_FILE: &str = "token";
+const READ_TOKEN_FILE: &str = "read-token";
+const SOCKET_FILE: &str = "claudy.sock";
+
+/// Secrets clients present when connecting to the server. The control token
+/// allows driving the mascot, the read token only observing it.
+#[derive(Debug, Clone)]
+pub struct Tokens {
+    pub control: String,
+    pub read: String,
+}
+
+/// Where the running app's server actually listens
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerInfo {
+    pub host: String,
+    pub port: u16,
+    pub pid: u32,
+}
+
+impl ServerInfo {
+    /// Address local clients should connect to
+    pub fn connect_host(&self) -> &str {
+        match self.host.as_str() {
+            "0.0.0.0" => "127.0.0.1",
+            "::" => "::1",
+            host => host,
+        }
+    }
+
+    pub fn ws_url(&self) -> String {
+        let host = self.connect_host();
+        if host.contains(':') {
+            // IPv6 addresses are bracketed in URLs
+            format!("ws://[{}]:{}", host, self.port)
+        } else {
+            format!("ws://{}:{}", host, self.port)
+        }
+    }
+}
+
+/// `$XDG_RUNTIME_DIR/claudy`, or a per-user directory under the temp dir
+/// where that isn't available
+pub fn runtime_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("XDG_RUNTIME_DIR").filter(|d| !d.is_empty()) {
+        return PathBuf::from(dir).join("claudy");
+    }
+
+    let user = std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "default".to_string());
+    std::env::temp_dir().join(format!("claudy-{}", user))
+}
+
+/// Control socket: `$XDG_RUNTIME_DIR/claudy.sock`, or inside the runtime dir
+/// where that isn't available
+pub fn socket_path() -> PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR").filter(|d| !d.is_empty()) {
+        Some(dir) => PathBuf::from(dir).join(SOCKET_FILE),
+        None => runtime_dir().join(SOCKET_FILE),
+    }
+}
+
+/// Create the runtime directory, readable only by the current user
+pub fn ensure_runtime_dir() -> io::Result<PathBuf> {
+    let dir = runtime_dir();
+    fs::create_dir_all(&dir)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o700))?;
+    }
+
+    Ok(dir)
+}
+
+pub fn write_server_info(info: &ServerInfo) -> io::Result<()> {
+    let path = ensure_runtime_dir()?.join(SERVER_INFO_FILE);
+    let content = serde_json::to_string_pretty(info).map_err(io::Error::other)?;
+    fs::write(path, content)
+}
+
+pub fn read_server_info() -> Option<ServerInfo> {
+    let content = fs::read_to_string(runtime_dir().join(SERVER_INFO_FILE)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Remove the server info, unless another process has taken it over since
+pub fn remove_server_info() {
+    if read_server_info().is_some_and(|info| info.pid == std::process::id()) {
+        let _ = fs::remove_file(runtime_dir().join(SERVER_INFO_FILE));
+    }
+}
+
+/// Load the tokens, generating any that don't exist yet
+pub fn load_or_create_tokens() -> io::Result<Tokens> {
+    Ok(Tokens {
+        control: load_or_create_token(CONTROL_TOKEN_FILE)?,
+        read: load_or_create_token(READ_TOKEN_FILE)?,
+    })
+}
+
+pub fn read_tokens() -> Option<Tokens> {
+    let dir = runtime_dir();
+    let read = |name: &str| {
+        fs::read_to_string(dir.join(name))
+            .ok()
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+    };
+    Some(Tokens {
+        control: read(CONTROL_TOKEN_FILE)?,
+        read: read(READ_TOKEN_FILE)?,
+    })
+}
+
+fn load_or_create_token(name: &str) -> io::Result<String> {
+    let path = ensure_runtime_dir()?.join(name);
+    if let Ok(existing) = fs::read_to_string(&path) {
+        let existing = existing.trim();
+        if !existing.is_empty() {
+            return Ok(existing.to_string());
+        }
+    }
+
+    let mut bytes = [0u8; 32];
+    getrandom::fill(&mut bytes).map_err(io::Error::other)?;
+    let token: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+
+    write_private(&path, &token)?;
+    Ok(token)
+}
+
+/// Write a file only the current user can read
+fn write_private(path: &std::path::Path, content: &str) -> io::Result<()> {
+    use std::io::Write;
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(content.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(host: &str) -> ServerInfo {
+        ServerInfo {
+            host: host.to_string(),
+            port: 3695,
+            pid: 1,
+        }
+    }
+
+    #[test]
+    fn ws_url_connects_to_loopback_for_wildcard_binds() {
+        assert_eq!(info("0.0.0.0").ws_url(), "ws://127.0.0.1:3695");
+        assert_eq!(info("::").ws_url(), "ws://[::1]:3695");
+    }
+
+    #[test]
+    fn ws_url_brackets_ipv6_hosts() {
+        assert_eq!(info("127.0.0.1").ws_url(), "ws://127.0.0.1:3695");
+        assert_eq!(info("fe80::1").ws_url(), "ws://[fe80::1]:3695");
+        assert_eq!(info("localhost").ws_url(), "ws://localhost:3695");
+    }
+}
@@ -0,0 +1,70 @@
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use super::{no_escape, render_template, EventPayload};
+use crate::config::CommandSinkConfig;
+use crate::pipeline::{Sink, StateUpdate};
+
+/// Commands still running after this long are killed
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Runs a local command per update, with the event as `CLAUDY_*` environment
+/// variables and as JSON on stdin
+pub struct CommandSink {
+    config: CommandSinkConfig,
+}
+
+impl CommandSink {
+    pub fn new(config: CommandSinkConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl Sink for CommandSink {
+    fn handle(&self, update: &StateUpdate) {
+        let payload = EventPayload::new(update);
+        let args: Vec<String> = self
+            .config
+            .args
+            .iter()
+            .map(|arg| render_template(arg, &payload, no_escape))
+            .collect();
+
+        let mut command = Command::new(&self.config.command);
+        command
+            .args(&args)
+            .envs(payload.env())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .kill_on_drop(true);
+
+        let stdin_json = payload.to_json();
+        let program = self.config.command.clone();
+
+        tokio::spawn(async move {
+            let mut child = match command.spawn() {
+                Ok(child) => child,
+                Err(e) => {
+                    eprintln!("[Claudy] Failed to run sink command {}: {}", program, e);
+                    return;
+                }
+            };
+
+            if let Some(mut stdin) = child.stdin.take() {
+                // The command may not read stdin at all; that's fine
+                let _ = stdin.write_all(stdin_json.as_bytes()).await;
+            }
+
+            match tokio::time::timeout(COMMAND_TIMEOUT, child.wait()).await {
+                Ok(Ok(status)) if !status.success() => {
+                    eprintln!("[Claudy] Sink command {} exited with {}", program, status);
+                }
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => eprintln!("[Claudy] Sink command {} failed: {}", program, e),
+                Err(_) => eprintln!("[Claudy] Sink command {} timed out", program),
+            }
+        });
+    }
+}
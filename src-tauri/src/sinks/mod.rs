@@ -0,0 +1,275 @@
+//! External sinks configured through `[[sinks]]` in the config file
+
+mod command;
+mod mqtt;
+mod webhook;
+
+use serde::Serialize;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tokio::runtime::Handle;
+use tokio::sync::watch;
+
+use crate::config::{Config, SinkConfig, SinkKind};
+use crate::pipeline::{Sink, StateUpdate};
+use crate::watcher::{path_to_slug, ClaudeEvent};
+
+pub use command::CommandSink;
+pub use mqtt::MqttSink;
+pub use webhook::WebhookSink;
+
+/// Flattened view of a [`StateUpdate`] handed to external consumers, both as
+/// JSON and as template placeholders.
+#[derive(Debug, Serialize)]
+pub struct EventPayload<'a> {
+    pub event: &'static str,
+    pub state: &'a str,
+    pub project: &'a str,
+    pub session: &'a str,
+    pub tool: Option<&'a str>,
+    pub file_path: Option<&'a str>,
+    pub message: Option<&'a str>,
+    pub success: Option<bool>,
+    pub active_projects: &'a [String],
+    pub focused_project: Option<&'a str>,
+}
+
+impl<'a> EventPayload<'a> {
+    pub fn new(update: &'a StateUpdate) -> Self {
+        let (tool, file_path, message, success) = match &update.event {
+            ClaudeEvent::ToolUse { tool, file_path, .. } => {
+                (Some(tool.as_str()), file_path.as_deref(), None, None)
+            }
+            ClaudeEvent::Error { message, .. } => (None, None, Some(message.as_str()), None),
+            ClaudeEvent::Stop { success, .. } => (None, None, None, Some(*success)),
+            _ => (None, None, None, None),
+        };
+
+        Self {
+            event: update.event.kind(),
+            state: &update.state.current_state,
+            project: update.event.project(),
+            session: update.event.session(),
+            tool,
+            file_path,
+            message,
+            success,
+            active_projects: &update.state.active_projects,
+            focused_project: update.state.focused_project.as_deref(),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    /// Value of a template placeholder; unknown names resolve to nothing
+    pub fn var(&self, name: &str) -> Option<String> {
+        match name {
+            "event" => Some(self.event.to_string()),
+            "state" => Some(self.state.to_string()),
+            "project" => Some(self.project.to_string()),
+            "session" => Some(self.session.to_string()),
+            "tool" => self.tool.map(str::to_string),
+            "file_path" => self.file_path.map(str::to_string),
+            "message" => self.message.map(str::to_string),
+            "success" => self.success.map(|s| s.to_string()),
+            "focused_project" => self.focused_project.map(str::to_string),
+            "json" => Some(self.to_json()),
+            _ => None,
+        }
+    }
+
+    /// Environment variables exposed to command sinks
+    pub fn env(&self) -> Vec<(String, String)> {
+        ["event", "state", "project", "session", "tool", "file_path", "message", "success"]
+            .iter()
+            .filter_map(|name| {
+                self.var(name)
+                    .map(|value| (format!("CLAUDY_{}", name.to_uppercase()), value))
+            })
+            .collect()
+    }
+}
+
+/// Replace `{{name}}` placeholders, passing each value through `escape`
+pub fn render_template(template: &str, payload: &EventPayload, escape: fn(&str) -> String) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        let name = rest[start + 2..start + 2 + len].trim();
+        if let Some(value) = payload.var(name) {
+            out.push_str(&escape(&value));
+        }
+        rest = &rest[start + 2 + len + 2..];
+    }
+
+    out.push_str(rest);
+    out
+}
+
+/// Escape a value for use inside a JSON string literal
+pub fn json_escape(value: &str) -> String {
+    let quoted = serde_json::to_string(value).unwrap_or_default();
+    quoted[1..quoted.len() - 1].to_string()
+}
+
+pub fn no_escape(value: &str) -> String {
+    value.to_string()
+}
+
+/// Wraps a sink with the event/project filters of its config entry
+struct FilteredSink<S> {
+    inner: S,
+    events: Vec<String>,
+    project_slugs: Vec<String>,
+}
+
+impl<S: Sink> Sink for FilteredSink<S> {
+    fn handle(&self, update: &StateUpdate) {
+        let kind = update.event.kind();
+        if !self.events.is_empty() && !self.events.iter().any(|e| e.eq_ignore_ascii_case(kind)) {
+            return;
+        }
+
+        let project = update.event.project();
+        if !self.project_slugs.is_empty() && !self.project_slugs.iter().any(|p| p == project) {
+            return;
+        }
+
+        self.inner.handle(update);
+    }
+}
+
+/// The sinks declared in the config, rebuilt when a reload changes them
+pub struct ConfiguredSinks {
+    sinks: Arc<Mutex<Vec<Box<dyn Sink>>>>,
+}
+
+impl ConfiguredSinks {
+    pub fn spawn(mut config: watch::Receiver<Arc<Config>>, runtime: &Handle) -> Self {
+        let mut current = config.borrow_and_update().sinks.clone();
+        let sinks = Arc::new(Mutex::new(build_sinks(&current, runtime)));
+
+        let rebuilt = sinks.clone();
+        let handle = runtime.clone();
+        runtime.spawn(async move {
+            while config.changed().await.is_ok() {
+                let configs = config.borrow_and_update().sinks.clone();
+                // Reloads follow every config save; keep connections otherwise
+                if configs == current {
+                    continue;
+                }
+                let sinks = build_sinks(&configs, &handle);
+                *rebuilt.lock().unwrap() = sinks;
+                current = configs;
+            }
+        });
+
+        Self { sinks }
+    }
+}
+
+impl Sink for ConfiguredSinks {
+    fn handle(&self, update: &StateUpdate) {
+        for sink in self.sinks.lock().unwrap().iter() {
+            sink.handle(update);
+        }
+    }
+}
+
+/// Build the sinks declared in the config. Entries that fail to initialize
+/// are logged and skipped.
+pub fn build_sinks(configs: &[SinkConfig], runtime: &Handle) -> Vec<Box<dyn Sink>> {
+    configs
+        .iter()
+        .filter_map(|cfg| {
+            let events = cfg.events.clone();
+            let project_slugs = cfg
+                .projects
+                .iter()
+                .map(|p| path_to_slug(Path::new(p)))
+                .collect();
+
+            let sink: Box<dyn Sink> = match &cfg.kind {
+                SinkKind::Webhook(c) => match WebhookSink::new(c.clone()) {
+                    Ok(inner) => Box::new(FilteredSink { inner, events, project_slugs }),
+                    Err(e) => {
+                        eprintln!("[Claudy] Skipping webhook sink {}: {}", c.url, e);
+                        return None;
+                    }
+                },
+                SinkKind::Command(c) => Box::new(FilteredSink {
+                    inner: CommandSink::new(c.clone()),
+                    events,
+                    project_slugs,
+                }),
+                SinkKind::Mqtt(c) => Box::new(FilteredSink {
+                    inner: MqttSink::new(c.clone(), runtime),
+                    events,
+                    project_slugs,
+                }),
+            };
+            Some(sink)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::ClaudyState;
+
+    fn tool_use(file_path: Option<&str>) -> StateUpdate {
+        let mut state = ClaudyState::new();
+        state.current_state = "working".to_string();
+        StateUpdate {
+            event: ClaudeEvent::ToolUse {
+                project: "-home-me-app".to_string(),
+                session: "abc".to_string(),
+                tool: "Edit".to_string(),
+                file_path: file_path.map(str::to_string),
+            },
+            state,
+        }
+    }
+
+    #[test]
+    fn render_template_fills_placeholders() {
+        let update = tool_use(Some("src/main.rs"));
+        let payload = EventPayload::new(&update);
+        assert_eq!(
+            render_template("{{ event }}: {{tool}} on {{file_path}} ({{state}})", &payload, no_escape),
+            "ToolUse: Edit on src/main.rs (working)"
+        );
+    }
+
+    #[test]
+    fn render_template_drops_missing_and_unknown_values() {
+        let update = tool_use(None);
+        let payload = EventPayload::new(&update);
+        assert_eq!(render_template("[{{file_path}}][{{nope}}]", &payload, no_escape), "[][]");
+    }
+
+    #[test]
+    fn render_template_keeps_unclosed_braces() {
+        let update = tool_use(None);
+        let payload = EventPayload::new(&update);
+        assert_eq!(render_template("{{tool}} {{tool", &payload, no_escape), "Edit {{tool");
+    }
+
+    #[test]
+    fn render_template_escapes_values() {
+        let update = tool_use(Some("a \"quoted\"\nname"));
+        let payload = EventPayload::new(&update);
+        assert_eq!(
+            render_template(r#"{"file": "{{file_path}}"}"#, &payload, json_escape),
+            r#"{"file": "a \"quoted\"\nname"}"#
+        );
+    }
+}
@@ -0,0 +1,78 @@
+use rumqttc::{AsyncClient, MqttOptions, QoS};
+use std::time::Duration;
+use tokio::runtime::Handle;
+use tokio::sync::oneshot;
+
+use super::{json_escape, no_escape, render_template, EventPayload};
+use crate::config::MqttSinkConfig;
+use crate::pipeline::{Sink, StateUpdate};
+
+/// Delay before polling the broker connection again after an error
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Publishes every update to an MQTT broker
+pub struct MqttSink {
+    client: AsyncClient,
+    config: MqttSinkConfig,
+    /// Stops the event loop once the sink is dropped
+    _stop: oneshot::Sender<()>,
+}
+
+impl MqttSink {
+    pub fn new(config: MqttSinkConfig, runtime: &Handle) -> Self {
+        let mut options = MqttOptions::new(&config.client_id, &config.host, config.port);
+        options.set_keep_alive(Duration::from_secs(30));
+        if let Some(username) = &config.username {
+            options.set_credentials(username, config.password.clone().unwrap_or_default());
+        }
+
+        let (client, mut eventloop) = AsyncClient::new(options, 32);
+        let (stop, mut stopped) = oneshot::channel();
+
+        // The event loop drives the connection and must be polled continuously
+        let host = format!("{}:{}", config.host, config.port);
+        runtime.spawn(async move {
+            let mut connected = false;
+            // Ends when the sink is dropped, e.g. rebuilt after a config reload
+            while stopped.try_recv() == Err(oneshot::error::TryRecvError::Empty) {
+                match eventloop.poll().await {
+                    Ok(_) => connected = true,
+                    Err(e) => {
+                        if connected {
+                            eprintln!("[Claudy] MQTT connection to {} lost: {}", host, e);
+                        }
+                        connected = false;
+                        tokio::time::sleep(RECONNECT_DELAY).await;
+                    }
+                }
+            }
+        });
+
+        Self {
+            client,
+            config,
+            _stop: stop,
+        }
+    }
+}
+
+impl Sink for MqttSink {
+    fn handle(&self, update: &StateUpdate) {
+        let payload = EventPayload::new(update);
+        let topic = render_template(&self.config.topic, &payload, no_escape);
+        let body = match &self.config.payload {
+            Some(template) => render_template(template, &payload, json_escape),
+            None => payload.to_json(),
+        };
+        let qos = match self.config.qos {
+            0 => QoS::AtMostOnce,
+            1 => QoS::AtLeastOnce,
+            _ => QoS::ExactlyOnce,
+        };
+
+        // try_publish never blocks; a full queue means the broker is unreachable
+        if let Err(e) = self.client.try_publish(topic, qos, self.config.retain, body) {
+            eprintln!("[Claudy] MQTT publish failed: {}", e);
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+use reqwest::{Client, Method};
+use std::time::Duration;
+
+use super::{json_escape, render_template, EventPayload};
+use crate::config::WebhookSinkConfig;
+use crate::pipeline::{Sink, StateUpdate};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Sends every update as an HTTP request with a JSON body
+pub struct WebhookSink {
+    client: Client,
+    method: Method,
+    config: WebhookSinkConfig,
+}
+
+impl WebhookSink {
+    pub fn new(config: WebhookSinkConfig) -> Result<Self, String> {
+        let method = Method::from_bytes(config.method.to_uppercase().as_bytes())
+            .map_err(|e| e.to_string())?;
+
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        for (name, value) in &config.headers {
+            let name = HeaderName::from_bytes(name.as_bytes()).map_err(|e| e.to_string())?;
+            let value = HeaderValue::from_str(value).map_err(|e| e.to_string())?;
+            headers.insert(name, value);
+        }
+
+        let client = Client::builder()
+            .default_headers(headers)
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|e| e.to_string())?;
+
+        Ok(Self {
+            client,
+            method,
+            config,
+        })
+    }
+}
+
+impl Sink for WebhookSink {
+    fn handle(&self, update: &StateUpdate) {
+        let payload = EventPayload::new(update);
+        let body = match &self.config.body {
+            Some(template) => render_template(template, &payload, json_escape),
+            None => payload.to_json(),
+        };
+
+        let request = self
+            .client
+            .request(self.method.clone(), &self.config.url)
+            .body(body);
+        let url = self.config.url.clone();
+
+        tokio::spawn(async move {
+            match request.send().await {
+                Ok(response) if !response.status().is_success() => {
+                    eprintln!("[Claudy] Webhook {} answered {}", url, response.status());
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("[Claudy] Webhook {} failed: {}", url, e),
+            }
+        });
+    }
+}
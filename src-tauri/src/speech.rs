@@ -0,0 +1,325 @@
+//! Speech bubble queue. Bubbles are shown one at a time, each for its
+//! duration, and the active one is part of every state snapshot.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::runtime::Handle;
+use tokio::sync::{mpsc, oneshot, watch};
+use tokio::time::Instant;
+
+use crate::config::Config;
+use crate::quiet::Quiet;
+use crate::state::SharedState;
+use crate::websocket::{broadcast_state, StateBroadcaster};
+
+/// How many bubbles may wait. When full, a bubble only gets in by evicting
+/// one of lower priority.
+const MAX_QUEUED: usize = 32;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    /// Interrupts whatever is being said
+    High,
+}
+
+/// A request to say something
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SayRequest {
+    pub text: String,
+    /// Seconds to show the bubble for, `notifications.bubble_duration` by default
+    #[serde(default)]
+    pub duration: Option<u32>,
+    #[serde(default)]
+    pub priority: Priority,
+    /// State to switch to while talking
+    #[serde(default)]
+    pub mood: Option<String>,
+}
+
+/// The bubble currently shown
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Bubble {
+    pub text: String,
+    /// Seconds the bubble stays up
+    pub duration: u32,
+    pub priority: Priority,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mood: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SayOutcome {
+    Shown,
+    Queued,
+    /// Same text is already shown or waiting
+    Duplicate,
+    /// Dropped during quiet hours or Do-Not-Disturb
+    Suppressed,
+    /// Dropped because the queue is full of bubbles of the same or higher
+    /// priority
+    QueueFull,
+}
+
+struct Say {
+    bubble: Bubble,
+    reply: oneshot::Sender<SayOutcome>,
+}
+
+/// Handle to the speech task
+#[derive(Clone)]
+pub struct Speaker {
+    requests: mpsc::Sender<Say>,
+    active: watch::Receiver<Option<Bubble>>,
+    quiet: Quiet,
+    /// For the default duration, following config reloads
+    config: watch::Receiver<Arc<Config>>,
+}
+
+impl Speaker {
+    /// Start the speech task on `runtime`. Bubble changes are written to the
+    /// shared state and broadcast to clients.
+    pub fn spawn(
+        runtime: &Handle,
+        state: SharedState,
+        broadcaster: StateBroadcaster,
+        quiet: Quiet,
+        config: watch::Receiver<Arc<Config>>,
+    ) -> Self {
+        let (requests_tx, requests_rx) = mpsc::channel(MAX_QUEUED);
+        let (active_tx, active_rx) = watch::channel(None);
+
+        let queue = SpeechQueue {
+            state,
+            broadcaster,
+            active: active_tx,
+            current: None,
+            waiting: VecDeque::new(),
+        };
+        runtime.spawn(queue.run(requests_rx));
+
+        Self {
+            requests: requests_tx,
+            active: active_rx,
+            quiet,
+            config,
+        }
+    }
+
+    /// Queue a bubble; `None` if the speech task has stopped
+    pub async fn say(&self, request: SayRequest) -> Option<SayOutcome> {
+        if self.quiet.is_quiet() {
+            return Some(SayOutcome::Suppressed);
+        }
+        let duration = request
+            .duration
+            .unwrap_or_else(|| self.config.borrow().notifications.bubble_duration)
+            .max(1);
+        let bubble = Bubble {
+            text: request.text,
+            duration,
+            priority: request.priority,
+            mood: request.mood,
+        };
+
+        let (reply_tx, reply_rx) = oneshot::channel();
+        let say = Say {
+            bubble,
+            reply: reply_tx,
+        };
+        self.requests.send(say).await.ok()?;
+        reply_rx.await.ok()
+    }
+
+    /// Follow the active bubble
+    pub fn subscribe(&self) -> watch::Receiver<Option<Bubble>> {
+        self.active.clone()
+    }
+}
+
+struct SpeechQueue {
+    state: SharedState,
+    broadcaster: StateBroadcaster,
+    active: watch::Sender<Option<Bubble>>,
+    current: Option<(Bubble, Instant)>,
+    waiting: VecDeque<Bubble>,
+}
+
+impl SpeechQueue {
+    async fn run(mut self, mut requests: mpsc::Receiver<Say>) {
+        loop {
+            let expiry = self.current.as_ref().map(|(_, until)| *until);
+            tokio::select! {
+                request = requests.recv() => match request {
+                    Some(Say { bubble, reply }) => {
+                        let _ = reply.send(self.enqueue(bubble));
+                    }
+                    None => break,
+                },
+                _ = sleep_until(expiry) => {
+                    self.current = None;
+                    self.advance();
+                }
+            }
+        }
+    }
+
+    fn enqueue(&mut self, bubble: Bubble) -> SayOutcome {
+        let is_duplicate = self
+            .current
+            .iter()
+            .map(|(b, _)| b)
+            .chain(self.waiting.iter())
+            .any(|b| b.text == bubble.text);
+        if is_duplicate {
+            return SayOutcome::Duplicate;
+        }
+
+        let interrupts = self
+            .current
+            .as_ref()
+            .is_some_and(|(current, _)| bubble.priority == Priority::High && current.priority < Priority::High);
+        if self.current.is_none() || interrupts {
+            // The interrupted bubble is said again right after
+            if let Some((interrupted, _)) = self.current.take() {
+                self.waiting.push_front(interrupted);
+                if self.waiting.len() > MAX_QUEUED {
+                    self.evict_lowest();
+                }
+            }
+            self.show(Some(bubble));
+            return SayOutcome::Shown;
+        }
+
+        if self.waiting.len() >= MAX_QUEUED {
+            // Sorted by priority, so the last one is the least important
+            if self.waiting.back().is_some_and(|b| b.priority < bubble.priority) {
+                self.evict_lowest();
+            } else {
+                return SayOutcome::QueueFull;
+            }
+        }
+
+        // Behind everything of the same or higher priority
+        let position = self
+            .waiting
+            .iter()
+            .position(|b| b.priority < bubble.priority)
+            .unwrap_or(self.waiting.len());
+        self.waiting.insert(position, bubble);
+        SayOutcome::Queued
+    }
+
+    fn evict_lowest(&mut self) {
+        if let Some(evicted) = self.waiting.pop_back() {
+            eprintln!("[Claudy] Speech queue full, dropped {:?}", evicted.text);
+        }
+    }
+
+    fn advance(&mut self) {
+        let next = self.waiting.pop_front();
+        self.show(next);
+    }
+
+    fn show(&mut self, bubble: Option<Bubble>) {
+        self.current = bubble
+            .clone()
+            .map(|b| (b.clone(), Instant::now() + Duration::from_secs(b.duration.into())));
+
+        let mut snapshot = {
+            let mut s = self.state.lock().unwrap();
+            s.bubble = bubble.clone();
+            s.clone()
+        };
+        // The mood only lasts for this broadcast, like a forced state
+        if let Some(mood) = bubble.as_ref().and_then(|b| b.mood.clone()) {
+            snapshot.current_state = mood;
+        }
+        broadcast_state(&self.broadcaster, &snapshot);
+        let _ = self.active.send(bubble);
+    }
+}
+
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::ClaudyState;
+    use crate::websocket::new_broadcaster;
+    use std::sync::Mutex;
+
+    fn queue() -> SpeechQueue {
+        SpeechQueue {
+            state: Arc::new(Mutex::new(ClaudyState::new())),
+            broadcaster: new_broadcaster(1),
+            active: watch::channel(None).0,
+            current: None,
+            waiting: VecDeque::new(),
+        }
+    }
+
+    fn bubble(text: &str, priority: Priority) -> Bubble {
+        Bubble {
+            text: text.to_string(),
+            duration: 5,
+            priority,
+            mood: None,
+        }
+    }
+
+    fn texts(queue: &SpeechQueue) -> Vec<&str> {
+        queue.waiting.iter().map(|b| b.text.as_str()).collect()
+    }
+
+    #[test]
+    fn high_priority_requeues_the_interrupted_bubble_first() {
+        let mut queue = queue();
+        assert_eq!(queue.enqueue(bubble("first", Priority::Normal)), SayOutcome::Shown);
+        assert_eq!(queue.enqueue(bubble("second", Priority::Normal)), SayOutcome::Queued);
+
+        assert_eq!(queue.enqueue(bubble("urgent", Priority::High)), SayOutcome::Shown);
+        assert_eq!(queue.current.as_ref().unwrap().0.text, "urgent");
+        assert_eq!(texts(&queue), vec!["first", "second"]);
+    }
+
+    #[test]
+    fn full_queue_rejects_bubbles_that_evict_nothing() {
+        let mut queue = queue();
+        queue.enqueue(bubble("shown", Priority::Normal));
+        for i in 0..MAX_QUEUED {
+            assert_eq!(queue.enqueue(bubble(&i.to_string(), Priority::Normal)), SayOutcome::Queued);
+        }
+
+        assert_eq!(queue.enqueue(bubble("late", Priority::Normal)), SayOutcome::QueueFull);
+        assert_eq!(queue.waiting.len(), MAX_QUEUED);
+        assert!(!texts(&queue).contains(&"late"));
+    }
+
+    #[test]
+    fn full_queue_evicts_the_lowest_priority_bubble() {
+        let mut queue = queue();
+        queue.enqueue(bubble("shown", Priority::Normal));
+        for i in 0..MAX_QUEUED - 1 {
+            queue.enqueue(bubble(&i.to_string(), Priority::Normal));
+        }
+        queue.enqueue(bubble("low", Priority::Low));
+
+        assert_eq!(queue.enqueue(bubble("normal", Priority::Normal)), SayOutcome::Queued);
+        assert_eq!(queue.waiting.len(), MAX_QUEUED);
+        assert!(!texts(&queue).contains(&"low"));
+        assert_eq!(texts(&queue).last(), Some(&"normal"));
+    }
+}
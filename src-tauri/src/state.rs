@@ -1,16 +1,22 @@
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
+use crate::speech::Bubble;
 use crate::watcher::ClaudeEvent;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
 pub struct ClaudyState {
     pub current_state: String,
     pub active_projects: Vec<String>,
+    /// State of each active project, from its own latest event
+    #[serde(default)]
+    pub project_states: HashMap<String, String>,
     pub focused_project: Option<String>,
     pub last_event: Option<ClaudeEvent>,
-    /// Optional bubble text for demos/external control
+    /// Speech bubble currently shown, see [`crate::speech`]
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub bubble_text: Option<String>,
+    pub bubble: Option<Bubble>,
     /// Suppress personality comments (for demos)
     #[serde(default)]
     pub suppress_comments: bool,
@@ -21,9 +27,10 @@ impl ClaudyState {
         Self {
             current_state: "idle".to_string(),
             active_projects: vec![],
+            project_states: HashMap::new(),
             focused_project: None,
             last_event: None,
-            bubble_text: None,
+            bubble: None,
             suppress_comments: false,
         }
     }
@@ -42,15 +49,15 @@ impl ClaudyState {
         };
 
         match &event {
-            ClaudeEvent::SessionStart { project } => {
+            ClaudeEvent::SessionStart { project, .. } => {
                 track_project(&mut self.active_projects, &mut self.focused_project, project);
                 self.current_state = "wake".to_string();
             }
-            ClaudeEvent::UserMessage { project } => {
+            ClaudeEvent::UserMessage { project, .. } => {
                 track_project(&mut self.active_projects, &mut self.focused_project, project);
                 self.current_state = "listening".to_string();
             }
-            ClaudeEvent::Thinking { project } => {
+            ClaudeEvent::Thinking { project, .. } => {
                 track_project(&mut self.active_projects, &mut self.focused_project, project);
                 self.current_state = "thinking".to_string();
             }
@@ -58,22 +65,31 @@ impl ClaudyState {
                 track_project(&mut self.active_projects, &mut self.focused_project, project);
                 self.current_state = "working".to_string();
             }
-            ClaudeEvent::Talking { project } => {
+            ClaudeEvent::Talking { project, .. } => {
                 track_project(&mut self.active_projects, &mut self.focused_project, project);
                 self.current_state = "talking".to_string();
             }
-            ClaudeEvent::WaitingForTask { project } => {
+            ClaudeEvent::WaitingForTask { project, .. } => {
                 track_project(&mut self.active_projects, &mut self.focused_project, project);
                 self.current_state = "sleepy".to_string();
             }
-            ClaudeEvent::Stop { project, success } => {
+            ClaudeEvent::Stop { project, success, .. } => {
                 track_project(&mut self.active_projects, &mut self.focused_project, project);
                 self.current_state = if *success { "happy" } else { "confused" }.to_string();
             }
+            ClaudeEvent::NeedsAttention { project, .. } => {
+                track_project(&mut self.active_projects, &mut self.focused_project, project);
+                self.current_state = "wake".to_string();
+            }
             ClaudeEvent::Error { .. } => {
                 self.current_state = "confused".to_string();
             }
         }
+
+        if self.active_projects.iter().any(|p| p == event.project()) {
+            self.project_states
+                .insert(event.project().to_string(), self.current_state.clone());
+        }
     }
 }
 
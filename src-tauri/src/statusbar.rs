@@ -0,0 +1,116 @@
+//! One-line renderings of the state for status bars (waybar, polybar,
+//! i3blocks, tmux) and scripts
+
+use serde_json::json;
+
+use crate::notifications::project_display_name;
+use crate::state::ClaudyState;
+use crate::watcher::ClaudeEvent;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BarFormat {
+    /// JSON with `text`, `tooltip`, `class` and `alt`, for `return-type = "json"`
+    Waybar,
+    /// Text with `%{F}` color tags
+    Polybar,
+    /// Plain text, for `interval=persist` blocks
+    I3blocks,
+    /// Text with `#[fg=]` color styles
+    Tmux,
+    Plain,
+    /// Everything, for scripts
+    Json,
+}
+
+/// What the bar shows, extracted from a state snapshot
+pub struct BarStatus {
+    pub mood: String,
+    pub project: Option<String>,
+    pub tool: Option<String>,
+    /// Display names of every active project
+    pub projects: Vec<String>,
+}
+
+impl BarStatus {
+    pub fn new(state: &ClaudyState, registered: &[String]) -> Self {
+        let name = |slug: &str| project_display_name(slug, registered);
+
+        let tool = match &state.last_event {
+            Some(ClaudeEvent::ToolUse { tool, .. }) if state.current_state == "working" => {
+                Some(tool.clone())
+            }
+            _ => None,
+        };
+
+        Self {
+            mood: state.current_state.clone(),
+            project: state.focused_project.as_deref().map(name),
+            tool,
+            projects: state.active_projects.iter().map(|p| name(p)).collect(),
+        }
+    }
+
+    /// `mood · project (tool)`
+    pub fn text(&self) -> String {
+        let mut text = self.mood.clone();
+        if let Some(project) = &self.project {
+            text.push_str(" · ");
+            text.push_str(project);
+        }
+        if let Some(tool) = &self.tool {
+            text.push_str(&format!(" ({})", tool));
+        }
+        text
+    }
+
+    pub fn tooltip(&self) -> String {
+        if self.projects.is_empty() {
+            return "No active sessions".to_string();
+        }
+        self.projects
+            .iter()
+            .map(|p| {
+                let marker = if Some(p) == self.project.as_ref() { "▸" } else { " " };
+                format!("{} {}", marker, p)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub fn render(&self, format: BarFormat) -> String {
+        let text = self.text();
+        match format {
+            BarFormat::Waybar => json!({
+                "text": text,
+                "tooltip": self.tooltip(),
+                "class": self.mood,
+                "alt": self.mood,
+            })
+            .to_string(),
+            BarFormat::Polybar => format!("%{{F{}}}{}%{{F-}}", mood_color(&self.mood), text.replace('%', "%%")),
+            BarFormat::Tmux => format!("#[fg={}]{}#[default]", mood_color(&self.mood), text.replace('#', "##")),
+            BarFormat::I3blocks | BarFormat::Plain => text,
+            BarFormat::Json => json!({
+                "text": text,
+                "mood": self.mood,
+                "project": self.project,
+                "tool": self.tool,
+                "projects": self.projects,
+            })
+            .to_string(),
+        }
+    }
+}
+
+/// Color for a mood, as `#rrggbb`
+pub fn mood_color(mood: &str) -> &'static str {
+    match mood {
+        "working" => "#f5a623",
+        "thinking" => "#bd93f9",
+        "listening" | "talking" => "#8be9fd",
+        "happy" => "#50fa7b",
+        "confused" => "#ff5555",
+        "wake" => "#f1fa8c",
+        _ => "#6272a4",
+    }
+}
@@ -0,0 +1,328 @@
+//! The tray's live parts: an icon and tooltip following Claudy's mood, a
+//! submenu of the active projects with their state, and the items acting on
+//! the running pipeline.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tauri::image::Image;
+use tauri::menu::{CheckMenuItem, MenuItem, Submenu};
+use tauri::tray::TrayIcon;
+use tauri::{AppHandle, Wry};
+use tokio::runtime::Handle;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::watch;
+
+use crate::auth::Scope;
+use crate::commands::ServerContext;
+use crate::config::Config;
+use crate::desktop;
+use crate::notifications::project_display_name;
+use crate::protocol::Command;
+use crate::state::ClaudyState;
+use crate::statusbar::{mood_color, BarStatus};
+use crate::watcher::transcript_path;
+use crate::websocket::Outgoing;
+
+/// Menu ids of the project entries are this followed by the project slug
+pub const PROJECT_ITEM_PREFIX: &str = "project:";
+
+/// Moods shown with the plain icon
+const RESTING_MOODS: [&str; 2] = ["idle", "sleepy"];
+
+/// Outline around the mood dot, so it stands out on any panel
+const DOT_OUTLINE: [u8; 4] = [0x1a, 0x1a, 0x2e, 0xff];
+
+#[derive(Default)]
+struct Seen {
+    state: ClaudyState,
+    /// Latest session of each project
+    sessions: HashMap<String, String>,
+    /// What the submenu and icon show, to leave them alone when unchanged
+    shown_projects: Option<Vec<(String, String, bool)>>,
+    shown_mood: Option<String>,
+}
+
+/// Tray items that change while Claudy runs. Cheap to clone.
+#[derive(Clone)]
+pub struct LiveTray {
+    /// Active projects; clicking one focuses it
+    pub projects: Submenu<Wry>,
+    pub pause: CheckMenuItem<Wry>,
+    /// Opens the focused project's current transcript
+    pub transcript: MenuItem<Wry>,
+    app: AppHandle,
+    icon: Image<'static>,
+    tray: Arc<Mutex<Option<TrayIcon>>>,
+    seen: Arc<Mutex<Seen>>,
+    /// For project names, following config reloads
+    config: watch::Receiver<Arc<Config>>,
+    context: ServerContext,
+    runtime: Handle,
+}
+
+impl LiveTray {
+    pub fn new(
+        app: &AppHandle,
+        icon: Image<'static>,
+        context: ServerContext,
+        runtime: &Handle,
+    ) -> tauri::Result<Self> {
+        Ok(Self {
+            projects: Submenu::with_id(app, "projects", "Projects", true)?,
+            pause: CheckMenuItem::with_id(
+                app,
+                "pause",
+                "Pause Watching",
+                true,
+                context.pipeline.is_paused(),
+                None::<&str>,
+            )?,
+            transcript: MenuItem::with_id(app, "open_transcript", "Open Transcript", false, None::<&str>)?,
+            app: app.clone(),
+            icon,
+            tray: Arc::default(),
+            seen: Arc::default(),
+            config: context.pipeline.subscribe_config(),
+            context,
+            runtime: runtime.clone(),
+        })
+    }
+
+    /// Follow state changes and quiet time in the given tray icon
+    pub fn spawn(&self, tray: TrayIcon) {
+        *self.tray.lock().unwrap() = Some(tray);
+        let state = self.context.state.lock().unwrap().clone();
+        self.update(&state);
+
+        let live = self.clone();
+        let mut updates = self.context.broadcaster.subscribe();
+        let mut quiet = self.context.quiet.subscribe();
+        let mut config = self.config.clone();
+        self.runtime.spawn(async move {
+            loop {
+                tokio::select! {
+                    message = updates.recv() => match message {
+                        Ok(Outgoing::Event(event)) => {
+                            live.seen
+                                .lock()
+                                .unwrap()
+                                .sessions
+                                .insert(event.project().to_string(), event.session().to_string());
+                        }
+                        Ok(Outgoing::State(state)) => live.update(&state),
+                        Err(RecvError::Lagged(_)) => {
+                            let state = live.context.state.lock().unwrap().clone();
+                            live.update(&state);
+                        }
+                        Err(RecvError::Closed) => break,
+                    },
+                    changed = quiet.changed() => {
+                        if changed.is_err() {
+                            break;
+                        }
+                        live.refresh();
+                    }
+                    changed = config.changed() => {
+                        if changed.is_err() {
+                            break;
+                        }
+                        // Registered names may have changed
+                        live.seen.lock().unwrap().shown_projects = None;
+                        live.refresh();
+                    }
+                }
+            }
+        });
+    }
+
+    /// Pause or resume reacting to Claude Code activity
+    pub fn toggle_pause(&self) {
+        let pipeline = &self.context.pipeline;
+        pipeline.set_paused(!pipeline.is_paused());
+        self.refresh();
+    }
+
+    /// Focus a project from the submenu, by its menu id
+    pub fn focus_project(&self, id: &str) {
+        let Some(project) = id.strip_prefix(PROJECT_ITEM_PREFIX) else {
+            return;
+        };
+        let command = Command::FocusProject {
+            project: project.to_string(),
+        };
+        // Clicking toggled the item; rebuild the submenu from the broadcast
+        // even if the focus stays the same
+        self.seen.lock().unwrap().shown_projects = None;
+        let context = self.context.clone();
+        self.runtime.spawn(async move {
+            if let Err(e) = context.execute(command, Scope::Control).await {
+                eprintln!("[Claudy] Failed to focus the project: {}", e.message);
+            }
+        });
+    }
+
+    /// Ask for a folder and register it as a project
+    pub fn register_folder(&self) {
+        let context = self.context.clone();
+        let runtime = self.runtime.clone();
+        thread::spawn(move || {
+            let Some(path) = desktop::pick_folder("Register a project folder") else {
+                return;
+            };
+            let path = path.to_string_lossy().to_string();
+            let command = Command::RegisterProject { path: path.clone() };
+            match runtime.block_on(context.execute(command, Scope::Control)) {
+                Ok(_) => println!("Registered {} from the tray", path),
+                Err(e) => eprintln!("[Claudy] Failed to register {}: {}", path, e.message),
+            }
+        });
+    }
+
+    /// Open the focused project's current transcript in the default app
+    pub fn open_transcript(&self) {
+        let Some(path) = transcript(&self.seen.lock().unwrap()) else {
+            return;
+        };
+        thread::spawn(move || {
+            if let Err(e) = desktop::open_path(&path) {
+                eprintln!("[Claudy] Failed to open {}: {}", path.display(), e);
+            }
+        });
+    }
+
+    fn update(&self, state: &ClaudyState) {
+        self.seen.lock().unwrap().state = state.clone();
+        self.refresh();
+    }
+
+    /// Bring every live item up to date. Runs on the main thread, as do menu
+    /// events, so updates from the state task and from clicks never overlap.
+    fn refresh(&self) {
+        let live = self.clone();
+        if let Err(e) = self.app.run_on_main_thread(move || live.refresh_now()) {
+            eprintln!("[Claudy] Failed to update the tray: {}", e);
+        }
+    }
+
+    fn refresh_now(&self) {
+        let registered = self.config.borrow().projects.registered.clone();
+        let (state, projects, mood, transcript) = {
+            let mut seen = self.seen.lock().unwrap();
+            let projects: Vec<_> = seen
+                .state
+                .active_projects
+                .iter()
+                .map(|slug| {
+                    let name = project_display_name(slug, &registered);
+                    let label = match seen.state.project_states.get(slug) {
+                        Some(mood) => format!("{} — {}", name, mood),
+                        None => name,
+                    };
+                    (slug.clone(), label, seen.state.focused_project.as_ref() == Some(slug))
+                })
+                .collect();
+            let projects = (seen.shown_projects.as_ref() != Some(&projects)).then(|| {
+                seen.shown_projects = Some(projects.clone());
+                projects
+            });
+
+            let mood = seen.state.current_state.clone();
+            let mood = (seen.shown_mood.as_ref() != Some(&mood)).then(|| {
+                seen.shown_mood = Some(mood.clone());
+                mood
+            });
+            (seen.state.clone(), projects, mood, transcript(&seen))
+        };
+
+        if let Some(projects) = projects {
+            if let Err(e) = self.show_projects(&projects) {
+                eprintln!("[Claudy] Failed to update the tray projects: {}", e);
+            }
+        }
+        let paused = self.context.pipeline.is_paused();
+        let _ = self.pause.set_checked(paused);
+        let _ = self.transcript.set_enabled(transcript.is_some());
+
+        let Some(tray) = self.tray.lock().unwrap().clone() else {
+            return;
+        };
+        if let Some(mood) = mood {
+            let _ = tray.set_icon(Some(mood_icon(&self.icon, &mood)));
+        }
+
+        let mut tooltip = format!("Claudy: {}", BarStatus::new(&state, &registered).text());
+        if paused {
+            tooltip.push_str("\nPaused");
+        }
+        let quiet = self.context.quiet.status();
+        if quiet.dnd {
+            tooltip.push_str("\nDo Not Disturb");
+        } else if quiet.quiet {
+            tooltip.push_str("\nQuiet hours");
+        }
+        let _ = tray.set_tooltip(Some(tooltip));
+    }
+
+    fn show_projects(&self, projects: &[(String, String, bool)]) -> tauri::Result<()> {
+        for item in self.projects.items()? {
+            self.projects.remove(&item)?;
+        }
+        if projects.is_empty() {
+            let none = MenuItem::with_id(&self.app, "no_projects", "No active sessions", false, None::<&str>)?;
+            return self.projects.append(&none);
+        }
+        for (slug, label, focused) in projects {
+            let id = format!("{}{}", PROJECT_ITEM_PREFIX, slug);
+            let item = CheckMenuItem::with_id(&self.app, id, label, true, *focused, None::<&str>)?;
+            self.projects.append(&item)?;
+        }
+        Ok(())
+    }
+}
+
+/// Transcript of the focused project's latest session, if it's still there
+fn transcript(seen: &Seen) -> Option<PathBuf> {
+    let project = seen.state.focused_project.as_ref()?;
+    let session = seen.sessions.get(project)?;
+    Some(transcript_path(project, session)).filter(|path| path.exists())
+}
+
+/// The icon with a dot in the mood's color in its bottom right corner
+fn mood_icon(icon: &Image<'_>, mood: &str) -> Image<'static> {
+    let (width, height) = (icon.width(), icon.height());
+    let mut rgba = icon.rgba().to_vec();
+
+    if !RESTING_MOODS.contains(&mood) {
+        let color = parse_color(mood_color(mood));
+        let radius = width.min(height) as f64 * 0.22;
+        let outline = (width.min(height) as f64 / 32.0).max(1.0);
+        let center_x = width as f64 - radius - outline;
+        let center_y = height as f64 - radius - outline;
+
+        for y in 0..height {
+            for x in 0..width {
+                let distance = (x as f64 + 0.5 - center_x).hypot(y as f64 + 0.5 - center_y);
+                let pixel = ((y * width + x) * 4) as usize;
+                if distance <= radius {
+                    rgba[pixel..pixel + 4].copy_from_slice(&color);
+                } else if distance <= radius + outline {
+                    rgba[pixel..pixel + 4].copy_from_slice(&DOT_OUTLINE);
+                }
+            }
+        }
+    }
+
+    Image::new(&rgba, width, height).to_owned()
+}
+
+/// `#rrggbb` as opaque RGBA
+fn parse_color(hex: &str) -> [u8; 4] {
+    let channel = |i: usize| {
+        hex.get(i..i + 2)
+            .and_then(|c| u8::from_str_radix(c, 16).ok())
+            .unwrap_or_default()
+    };
+    [channel(1), channel(3), channel(5), 0xff]
+}
@@ -1,125 +1,341 @@
 use notify::{Config, Event, RecommendedWatcher, RecursiveMode, Watcher};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
-use std::fs::File;
+use std::fs::{self, File, Metadata};
 use std::io::{Read, Seek, SeekFrom};
 use std::path::{Path, PathBuf};
-use std::sync::mpsc::channel;
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::mpsc;
 
+/// How much of the most recent transcript to read when reconstructing state at startup
+const BACKFILL_TAIL_BYTES: u64 = 64 * 1024;
+
+/// Sessions untouched for longer than this are not replayed at startup
+const BACKFILL_MAX_AGE: Duration = Duration::from_secs(30 * 60);
+
+/// Transcripts are read in chunks of this size so huge appends never sit in memory at once
+const READ_CHUNK_BYTES: usize = 256 * 1024;
+
+/// Lines longer than this are dropped instead of being buffered
+const MAX_LINE_BYTES: usize = 16 * 1024 * 1024;
+
+/// Files without activity for this long stop being tracked
+const FILE_STATE_IDLE_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// How often tracked files are checked for eviction
+const SWEEP_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Watch source of the event pipeline: owns the notify watcher on
+/// `~/.claude/projects` and decides which projects are of interest.
 pub struct SessionWatcher {
     watcher: RecommendedWatcher,
-    watched_projects: WatchedProjects,
+    reader: TranscriptReader,
     claude_projects_root: PathBuf,
+    has_projects: bool,
     is_root_watched: bool,
     has_logged_missing_root: bool,
+    last_sweep: Instant,
+}
+
+/// A transcript change reported by the watch source
+#[derive(Debug, Clone)]
+pub enum FsChange {
+    Modified(PathBuf),
+    Removed(PathBuf),
+}
+
+/// Parser stage: turns transcript changes into [`ClaudeEvent`]s. Cheap to
+/// clone so it can be moved onto a blocking task.
+#[derive(Clone)]
+pub struct TranscriptReader {
+    file_states: FileStates,
+    watched_projects: WatchedProjects,
 }
 
 type WatchedProjects = Arc<Mutex<HashSet<String>>>;
 
-#[derive(Default)]
 struct FileReadState {
     offset: u64,
-    pending: String,
+    /// Bytes of an unterminated line carried over to the next read
+    pending: Vec<u8>,
+    /// Set after an oversized line was dropped, until its terminating newline is seen
+    skipping_oversized: bool,
+    last_activity: Instant,
+}
+
+#[derive(Default)]
+struct FileTracker {
+    files: HashMap<PathBuf, FileReadState>,
+    /// Where reading left off in transcripts without an entry in `files`:
+    /// evicted ones, and old ones that predate the watcher. Only forgotten
+    /// once the file is deleted.
+    offsets: HashMap<PathBuf, u64>,
+    evicted_files: u64,
+    oversized_lines: u64,
 }
 
-type FileStates = Arc<Mutex<HashMap<PathBuf, FileReadState>>>;
+type FileStates = Arc<Mutex<FileTracker>>;
+
+/// Snapshot of the watcher's bookkeeping, for diagnostics
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct WatcherMetrics {
+    pub tracked_files: usize,
+    pub pending_bytes: usize,
+    pub evicted_files: u64,
+    pub oversized_lines: u64,
+}
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Cloneable handle for reading [`WatcherMetrics`] from other threads
+#[derive(Clone)]
+pub struct WatcherStats(FileStates);
+
+impl WatcherStats {
+    pub fn snapshot(&self) -> WatcherMetrics {
+        let tracker = self.0.lock().unwrap();
+        WatcherMetrics {
+            tracked_files: tracker.files.len(),
+            pending_bytes: tracker.files.values().map(|f| f.pending.len()).sum(),
+            evicted_files: tracker.evicted_files,
+            oversized_lines: tracker.oversized_lines,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub enum ClaudeEvent {
     SessionStart {
         project: String,
+        session: String,
     },
     UserMessage {
         project: String,
+        session: String,
     },
     Thinking {
         project: String,
+        session: String,
     },
     ToolUse {
         project: String,
+        session: String,
         tool: String,
         file_path: Option<String>,
     },
     Talking {
         project: String,
+        session: String,
     },
     WaitingForTask {
         project: String,
+        session: String,
     },
     Stop {
         project: String,
+        session: String,
         success: bool,
     },
     Error {
         project: String,
+        session: String,
         message: String,
     },
+    NeedsAttention {
+        project: String,
+        session: String,
+    },
 }
 
-impl SessionWatcher {
-    pub fn new<F>(callback: F) -> Result<Self, notify::Error>
-    where
-        F: Fn(ClaudeEvent) + Send + 'static,
-    {
-        let (tx, rx) = channel();
-        let file_states: FileStates = Arc::new(Mutex::new(HashMap::new()));
-        let watched_projects: WatchedProjects = Arc::new(Mutex::new(HashSet::new()));
+impl ClaudeEvent {
+    /// Variant name, as used in serialized events and sink filters
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ClaudeEvent::SessionStart { .. } => "SessionStart",
+            ClaudeEvent::UserMessage { .. } => "UserMessage",
+            ClaudeEvent::Thinking { .. } => "Thinking",
+            ClaudeEvent::ToolUse { .. } => "ToolUse",
+            ClaudeEvent::Talking { .. } => "Talking",
+            ClaudeEvent::WaitingForTask { .. } => "WaitingForTask",
+            ClaudeEvent::Stop { .. } => "Stop",
+            ClaudeEvent::Error { .. } => "Error",
+            ClaudeEvent::NeedsAttention { .. } => "NeedsAttention",
+        }
+    }
 
+    /// Id of the session (transcript file name) the event came from
+    pub fn session(&self) -> &str {
+        match self {
+            ClaudeEvent::SessionStart { session, .. }
+            | ClaudeEvent::UserMessage { session, .. }
+            | ClaudeEvent::Thinking { session, .. }
+            | ClaudeEvent::ToolUse { session, .. }
+            | ClaudeEvent::Talking { session, .. }
+            | ClaudeEvent::WaitingForTask { session, .. }
+            | ClaudeEvent::Stop { session, .. }
+            | ClaudeEvent::Error { session, .. }
+            | ClaudeEvent::NeedsAttention { session, .. } => session,
+        }
+    }
+
+    /// Project slug the event belongs to
+    pub fn project(&self) -> &str {
+        match self {
+            ClaudeEvent::SessionStart { project, .. }
+            | ClaudeEvent::UserMessage { project, .. }
+            | ClaudeEvent::Thinking { project, .. }
+            | ClaudeEvent::ToolUse { project, .. }
+            | ClaudeEvent::Talking { project, .. }
+            | ClaudeEvent::WaitingForTask { project, .. }
+            | ClaudeEvent::Stop { project, .. }
+            | ClaudeEvent::Error { project, .. }
+            | ClaudeEvent::NeedsAttention { project, .. } => project,
+        }
+    }
+}
+
+impl SessionWatcher {
+    /// Create the watcher. Transcript changes are sent to `changes`; the
+    /// channel is bounded, so a slow consumer applies backpressure to notify.
+    pub fn new(changes: mpsc::Sender<FsChange>) -> Result<Self, notify::Error> {
         let watcher = RecommendedWatcher::new(
             move |res: Result<Event, notify::Error>| {
                 if let Ok(event) = res {
-                    let _ = tx.send(event);
+                    for change in to_fs_changes(event) {
+                        if changes.blocking_send(change).is_err() {
+                            return; // Pipeline shut down
+                        }
+                    }
                 }
             },
             Config::default().with_poll_interval(Duration::from_millis(500)),
         )?;
 
-        // Spawn thread to process events
-        let states = file_states.clone();
-        let projects = watched_projects.clone();
-        std::thread::spawn(move || {
-            while let Ok(event) = rx.recv() {
-                let events = parse_file_events(&event, &states, &projects);
-                for claude_event in events {
-                    callback(claude_event);
-                }
-            }
-        });
-
-        let claude_projects_root = dirs::home_dir()
-            .unwrap_or_else(|| PathBuf::from("/"))
-            .join(".claude")
-            .join("projects");
+        let claude_projects_root = claude_projects_root();
 
         Ok(Self {
             watcher,
-            watched_projects,
+            reader: TranscriptReader {
+                file_states: Arc::new(Mutex::new(FileTracker::default())),
+                watched_projects: Arc::new(Mutex::new(HashSet::new())),
+            },
             claude_projects_root,
+            has_projects: false,
             is_root_watched: false,
             has_logged_missing_root: false,
+            last_sweep: Instant::now(),
         })
     }
 
-    pub fn sync_projects(&mut self, project_paths: &[String]) -> Result<(), notify::Error> {
+    pub fn reader(&self) -> TranscriptReader {
+        self.reader.clone()
+    }
+
+    pub fn stats(&self) -> WatcherStats {
+        WatcherStats(self.reader.file_states.clone())
+    }
+
+    /// Update the set of watched projects, returning the reconstructed
+    /// latest state of any project that wasn't watched before.
+    pub fn sync_projects(&mut self, project_paths: &[String]) -> Result<Vec<ClaudeEvent>, notify::Error> {
         let slugs: HashSet<String> = project_paths
             .iter()
             .map(|path| path_to_slug(Path::new(path)))
             .collect();
 
+        let new_slugs: Vec<String> = {
+            let watched = self.reader.watched_projects.lock().unwrap();
+            slugs.difference(&watched).cloned().collect()
+        };
+
+        // Seek existing transcripts to their end *before* the project becomes
+        // watched, so the parser never replays history for them.
+        let backfilled = self.backfill_projects(&new_slugs);
+
         {
-            let mut watched = self.watched_projects.lock().unwrap();
+            let mut watched = self.reader.watched_projects.lock().unwrap();
             *watched = slugs;
         }
 
-        if project_paths.is_empty() {
-            return Ok(());
+        self.has_projects = !project_paths.is_empty();
+        if self.has_projects {
+            self.ensure_root_watch()?;
         }
 
-        self.ensure_root_watch()?;
+        Ok(backfilled)
+    }
 
-        Ok(())
+    /// Periodic maintenance: pick up a late-created projects root and evict
+    /// stale transcript bookkeeping.
+    pub fn housekeeping(&mut self) {
+        if self.has_projects {
+            if let Err(e) = self.ensure_root_watch() {
+                eprintln!("Failed to watch {}: {}", self.claude_projects_root.display(), e);
+            }
+        }
+
+        if self.last_sweep.elapsed() >= SWEEP_INTERVAL {
+            sweep_file_states(&self.reader.file_states);
+            self.last_sweep = Instant::now();
+        }
+    }
+
+    /// Mark every existing transcript of the given projects as read and
+    /// reconstruct the latest state of each from its most recent session.
+    fn backfill_projects(&self, slugs: &[String]) -> Vec<ClaudeEvent> {
+        let mut latest_events: Vec<(SystemTime, ClaudeEvent)> = slugs
+            .iter()
+            .filter_map(|slug| self.backfill_project(slug))
+            .collect();
+
+        // Replay oldest first so the most recently active project wins
+        latest_events.sort_by_key(|(modified, _)| *modified);
+        latest_events.into_iter().map(|(_, event)| event).collect()
+    }
+
+    fn backfill_project(&self, slug: &str) -> Option<(SystemTime, ClaudeEvent)> {
+        let entries = fs::read_dir(self.claude_projects_root.join(slug)).ok()?;
+        let mut most_recent: Option<(SystemTime, PathBuf)> = None;
+
+        {
+            let mut tracker = self.reader.file_states.lock().unwrap();
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().and_then(|e| e.to_str()) != Some("jsonl") {
+                    continue;
+                }
+                let meta = match entry.metadata() {
+                    Ok(meta) => meta,
+                    Err(_) => continue,
+                };
+
+                let modified = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+
+                // Old transcripts only need their offset, in case the
+                // session is resumed later
+                if is_recent(modified) {
+                    tracker
+                        .files
+                        .insert(path.clone(), FileReadState::at(meta.len()));
+                } else {
+                    tracker.offsets.insert(path.clone(), meta.len());
+                }
+
+                if most_recent.as_ref().is_none_or(|(m, _)| modified > *m) {
+                    most_recent = Some((modified, path));
+                }
+            }
+        }
+
+        let (modified, path) = most_recent?;
+        let age = modified.elapsed().unwrap_or_default();
+        if age > BACKFILL_MAX_AGE {
+            return None;
+        }
+
+        let event = read_tail_event(&path, slug)?;
+        eprintln!("[Claudy] Backfilled {} from {}: {:?}", slug, path.display(), event);
+        Some((modified, event))
     }
 
     fn ensure_root_watch(&mut self) -> Result<(), notify::Error> {
@@ -147,30 +363,168 @@ impl SessionWatcher {
     }
 }
 
-fn path_to_slug(path: &Path) -> String {
+/// Where Claude Code keeps its transcripts, one directory per project slug
+pub fn claude_projects_root() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("/"))
+        .join(".claude")
+        .join("projects")
+}
+
+/// Transcript file of a session, see [`session_id`]
+pub fn transcript_path(project: &str, session: &str) -> PathBuf {
+    claude_projects_root().join(project).join(format!("{}.jsonl", session))
+}
+
+pub fn path_to_slug(path: &Path) -> String {
     path.to_string_lossy()
         .replace("/", "-")
         .replace(".", "")
         .to_string()
 }
 
-fn parse_file_events(
-    event: &Event,
-    file_states: &FileStates,
-    watched_projects: &WatchedProjects,
-) -> Vec<ClaudeEvent> {
-    let mut results = Vec::new();
+impl FileReadState {
+    fn at(offset: u64) -> Self {
+        Self {
+            offset,
+            pending: Vec::new(),
+            skipping_oversized: false,
+            last_activity: Instant::now(),
+        }
+    }
 
-    // Only care about modify events
-    if !event.kind.is_modify() {
-        return results;
+    /// State for a file we have no record of. Transcripts that existed when
+    /// their project was first watched all have an offset, so this is a new
+    /// session and is read from the start, unless it's known to be old (e.g.
+    /// moved in from elsewhere).
+    fn untracked(meta: &Metadata) -> Self {
+        let is_new = meta.created().map(is_recent).unwrap_or(true);
+        Self::at(if is_new { 0 } else { meta.len() })
     }
 
-    for path in &event.paths {
-        results.extend(parse_jsonl_file(path, file_states, watched_projects));
+    /// Feed freshly read bytes, returning the completed lines and the number
+    /// of oversized lines dropped along the way.
+    fn feed(&mut self, mut bytes: &[u8]) -> (Vec<String>, u64) {
+        let mut lines = Vec::new();
+        let mut dropped = 0;
+
+        while let Some(newline) = bytes.iter().position(|b| *b == b'\n') {
+            let line = &bytes[..newline];
+            bytes = &bytes[newline + 1..];
+
+            if self.skipping_oversized {
+                // Tail end of a line we already gave up on
+                self.skipping_oversized = false;
+                continue;
+            }
+
+            if self.pending.len() + line.len() > MAX_LINE_BYTES {
+                self.pending = Vec::new();
+                dropped += 1;
+                continue;
+            }
+
+            let text = if self.pending.is_empty() {
+                String::from_utf8_lossy(line).into_owned()
+            } else {
+                self.pending.extend_from_slice(line);
+                String::from_utf8_lossy(&std::mem::take(&mut self.pending)).into_owned()
+            };
+            lines.push(text.trim_end_matches('\r').to_string());
+        }
+
+        if !self.skipping_oversized {
+            if self.pending.len() + bytes.len() > MAX_LINE_BYTES {
+                self.pending = Vec::new();
+                self.skipping_oversized = true;
+                dropped += 1;
+            } else {
+                self.pending.extend_from_slice(bytes);
+            }
+        }
+
+        (lines, dropped)
     }
+}
 
-    results
+fn is_recent(time: SystemTime) -> bool {
+    time.elapsed().unwrap_or_default() < FILE_STATE_IDLE_TTL
+}
+
+/// Drop bookkeeping for transcripts that were deleted or have gone quiet.
+/// Quiet ones keep their offset, so a resumed session carries on where it
+/// left off.
+fn sweep_file_states(file_states: &FileStates) {
+    let mut tracker = file_states.lock().unwrap();
+    let tracker = &mut *tracker;
+    let before = tracker.files.len();
+    tracker.offsets.retain(|path, _| path.exists());
+    tracker.files.retain(|path, state| {
+        if state.last_activity.elapsed() < FILE_STATE_IDLE_TTL {
+            return true;
+        }
+        if path.exists() {
+            // Re-read an unterminated line from its start
+            let offset = state.offset - state.pending.len() as u64;
+            tracker.offsets.insert(path.clone(), offset);
+        }
+        false
+    });
+
+    let evicted = before - tracker.files.len();
+    if evicted > 0 {
+        tracker.evicted_files += evicted as u64;
+        eprintln!(
+            "[Claudy] Evicted {} idle transcript(s), still tracking {}",
+            evicted,
+            tracker.files.len()
+        );
+    }
+}
+
+fn to_fs_changes(event: Event) -> Vec<FsChange> {
+    let is_remove = event.kind.is_remove();
+    if !is_remove && !event.kind.is_modify() {
+        return Vec::new();
+    }
+
+    event
+        .paths
+        .into_iter()
+        .filter(|path| path.extension().and_then(|e| e.to_str()) == Some("jsonl"))
+        .map(|path| {
+            if is_remove {
+                FsChange::Removed(path)
+            } else {
+                FsChange::Modified(path)
+            }
+        })
+        .collect()
+}
+
+impl TranscriptReader {
+    /// Read whatever was appended to the changed transcripts. Does blocking
+    /// file IO, so callers on an async runtime should use a blocking task.
+    pub fn read_changes(&self, changes: &[FsChange]) -> Vec<ClaudeEvent> {
+        let mut results = Vec::new();
+
+        for change in changes {
+            match change {
+                FsChange::Modified(path) => {
+                    results.extend(parse_jsonl_file(path, &self.file_states, &self.watched_projects));
+                }
+                FsChange::Removed(path) => {
+                    let mut tracker = self.file_states.lock().unwrap();
+                    tracker.offsets.remove(path);
+                    if tracker.files.remove(path).is_some() {
+                        tracker.evicted_files += 1;
+                    }
+                }
+            }
+        }
+
+        results
+    }
 }
 
 fn parse_jsonl_file(
@@ -201,95 +555,113 @@ fn parse_jsonl_file(
     if !is_project_watched(&project, watched_projects) {
         return results;
     }
+    let session = session_id(path);
 
     let mut file = match File::open(path) {
         Ok(f) => f,
         Err(_) => return results,
     };
 
-    let file_len = match file.metadata() {
-        Ok(meta) => meta.len(),
+    let meta = match file.metadata() {
+        Ok(meta) => meta,
         Err(_) => return results,
     };
 
-    let (start_offset, pending_prefix) = {
-        let mut states = file_states.lock().unwrap();
-        let state = states.entry(path.to_path_buf()).or_default();
-
-        // File got truncated/rotated
-        if file_len < state.offset {
-            state.offset = 0;
-            state.pending.clear();
+    let mut state = {
+        let mut tracker = file_states.lock().unwrap();
+        match tracker.files.remove(path) {
+            Some(state) => state,
+            None => match tracker.offsets.remove(path) {
+                Some(offset) => FileReadState::at(offset),
+                None => FileReadState::untracked(&meta),
+            },
         }
-
-        (state.offset, std::mem::take(&mut state.pending))
     };
 
-    if file.seek(SeekFrom::Start(start_offset)).is_err() {
-        return results;
-    }
-
-    let mut bytes = Vec::new();
-    if file.read_to_end(&mut bytes).is_err() {
-        return results;
+    // File got truncated/rotated
+    if meta.len() < state.offset {
+        state = FileReadState::at(0);
     }
 
-    let new_offset = start_offset + bytes.len() as u64;
-
-    let mut buffer = pending_prefix;
-    buffer.push_str(&String::from_utf8_lossy(&bytes));
-
-    let (complete_lines, pending_tail) = split_complete_lines(buffer);
+    let mut dropped = 0;
+    if file.seek(SeekFrom::Start(state.offset)).is_ok() {
+        let mut chunk = vec![0u8; READ_CHUNK_BYTES];
+        loop {
+            let read = match file.read(&mut chunk) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => n,
+            };
+            state.offset += read as u64;
+
+            let (lines, chunk_dropped) = state.feed(&chunk[..read]);
+            dropped += chunk_dropped;
+
+            for line in lines {
+                if line.is_empty() {
+                    continue;
+                }
 
-    for line in complete_lines {
-        if line.is_empty() {
-            continue;
+                if let Some(claude_event) = parse_jsonl_line(&line, &project, &session) {
+                    eprintln!("[Claudy] Parsed event: {:?}", claude_event);
+                    results.push(claude_event);
+                }
+            }
         }
+    }
 
-        if let Some(claude_event) = parse_jsonl_line(&line, &project) {
-            eprintln!("[Claudy] Parsed event: {:?}", claude_event);
-            results.push(claude_event);
-        }
+    if dropped > 0 {
+        eprintln!(
+            "[Claudy] Dropped {} line(s) over {} bytes in {}",
+            dropped,
+            MAX_LINE_BYTES,
+            path.display()
+        );
     }
 
-    let mut states = file_states.lock().unwrap();
-    let state = states.entry(path.to_path_buf()).or_default();
-    state.offset = new_offset;
-    state.pending = pending_tail;
+    state.last_activity = Instant::now();
+    let mut tracker = file_states.lock().unwrap();
+    tracker.oversized_lines += dropped;
+    tracker.files.insert(path.to_path_buf(), state);
 
     results
 }
 
-fn split_complete_lines(mut buffer: String) -> (Vec<String>, String) {
-    if buffer.is_empty() {
-        return (Vec::new(), String::new());
-    }
+/// Parse the tail of a transcript and return the last recognizable event
+fn read_tail_event(path: &Path, project: &str) -> Option<ClaudeEvent> {
+    let session = session_id(path);
+    let mut file = File::open(path).ok()?;
+    let file_len = file.metadata().ok()?.len();
+    let start = file_len.saturating_sub(BACKFILL_TAIL_BYTES);
 
-    if buffer.ends_with('\n') {
-        let lines = buffer
-            .lines()
-            .map(|line| line.trim_end_matches('\r').to_string())
-            .collect();
-        return (lines, String::new());
-    }
+    file.seek(SeekFrom::Start(start)).ok()?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).ok()?;
 
-    if let Some(last_newline) = buffer.rfind('\n') {
-        let pending = buffer.split_off(last_newline + 1);
-        let lines = buffer
-            .lines()
-            .map(|line| line.trim_end_matches('\r').to_string())
-            .collect();
-        return (lines, pending);
+    let text = String::from_utf8_lossy(&bytes);
+    let mut lines = text.lines();
+    if start > 0 {
+        // First line is most likely cut in half
+        lines.next();
     }
 
-    (Vec::new(), buffer)
+    lines
+        .rev()
+        .find_map(|line| parse_jsonl_line(line.trim_end_matches('\r'), project, &session))
+}
+
+/// Claude Code names each transcript after its session id
+fn session_id(path: &Path) -> String {
+    path.file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default()
+        .to_string()
 }
 
 fn is_project_watched(project: &str, watched_projects: &WatchedProjects) -> bool {
     watched_projects.lock().unwrap().contains(project)
 }
 
-fn parse_jsonl_line(line: &str, project: &str) -> Option<ClaudeEvent> {
+fn parse_jsonl_line(line: &str, project: &str, session: &str) -> Option<ClaudeEvent> {
     let json: serde_json::Value = serde_json::from_str(line).ok()?;
 
     let event_type = json.get("type")?.as_str()?;
@@ -322,6 +694,7 @@ fn parse_jsonl_line(line: &str, project: &str) -> Option<ClaudeEvent> {
             }
             Some(ClaudeEvent::UserMessage {
                 project: project.to_string(),
+                session: session.to_string(),
             })
         }
         "assistant" => {
@@ -337,6 +710,7 @@ fn parse_jsonl_line(line: &str, project: &str) -> Option<ClaudeEvent> {
                             Some("thinking") => {
                                 return Some(ClaudeEvent::Thinking {
                                     project: project.to_string(),
+                                    session: session.to_string(),
                                 });
                             }
                             Some("tool_use") => {
@@ -359,6 +733,7 @@ fn parse_jsonl_line(line: &str, project: &str) -> Option<ClaudeEvent> {
 
                                 return Some(ClaudeEvent::ToolUse {
                                     project: project.to_string(),
+                                    session: session.to_string(),
                                     tool: tool_name,
                                     file_path,
                                 });
@@ -366,6 +741,7 @@ fn parse_jsonl_line(line: &str, project: &str) -> Option<ClaudeEvent> {
                             Some("text") => {
                                 return Some(ClaudeEvent::Talking {
                                     project: project.to_string(),
+                                    session: session.to_string(),
                                 });
                             }
                             _ => {}
@@ -385,16 +761,23 @@ fn parse_jsonl_line(line: &str, project: &str) -> Option<ClaudeEvent> {
                     match hook_event {
                         "SessionStart" => Some(ClaudeEvent::SessionStart {
                             project: project.to_string(),
+                            session: session.to_string(),
                         }),
                         "Stop" => Some(ClaudeEvent::Stop {
                             project: project.to_string(),
+                            session: session.to_string(),
                             success: true,
                         }),
+                        "Notification" => Some(ClaudeEvent::NeedsAttention {
+                            project: project.to_string(),
+                            session: session.to_string(),
+                        }),
                         _ => None,
                     }
                 }
                 "waiting_for_task" => Some(ClaudeEvent::WaitingForTask {
                     project: project.to_string(),
+                    session: session.to_string(),
                 }),
                 _ => None,
             }
@@ -407,6 +790,7 @@ fn parse_jsonl_line(line: &str, project: &str) -> Option<ClaudeEvent> {
                     if !errors.is_empty() {
                         return Some(ClaudeEvent::Error {
                             project: project.to_string(),
+                            session: session.to_string(),
                             message: "Hook error".to_string(),
                         });
                     }
@@ -417,3 +801,72 @@ fn parse_jsonl_line(line: &str, project: &str) -> Option<ClaudeEvent> {
         _ => None,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn feed_joins_lines_split_across_reads() {
+        let mut state = FileReadState::at(0);
+
+        let (lines, dropped) = state.feed(b"{\"a\":1}\n{\"b\"");
+        assert_eq!(lines, vec!["{\"a\":1}"]);
+        assert_eq!(dropped, 0);
+
+        let (lines, _) = state.feed(b":2}\r\n");
+        assert_eq!(lines, vec!["{\"b\":2}"]);
+        assert!(state.pending.is_empty());
+    }
+
+    #[test]
+    fn feed_drops_oversized_line_within_a_read() {
+        let mut state = FileReadState::at(0);
+        let mut bytes = vec![b'x'; MAX_LINE_BYTES + 1];
+        bytes.extend_from_slice(b"\nok\n");
+
+        let (lines, dropped) = state.feed(&bytes);
+        assert_eq!(lines, vec!["ok"]);
+        assert_eq!(dropped, 1);
+        assert!(!state.skipping_oversized);
+    }
+
+    #[test]
+    fn feed_skips_oversized_line_until_its_newline() {
+        let mut state = FileReadState::at(0);
+
+        let (lines, dropped) = state.feed(&vec![b'x'; MAX_LINE_BYTES + 1]);
+        assert!(lines.is_empty());
+        assert_eq!(dropped, 1);
+        assert!(state.skipping_oversized);
+        assert!(state.pending.is_empty());
+
+        // More of the same line, still no newline
+        let (lines, dropped) = state.feed(b"xxxx");
+        assert!(lines.is_empty());
+        assert_eq!(dropped, 0);
+
+        // Recovers on the next newline
+        let (lines, dropped) = state.feed(b"tail\nnext\n");
+        assert_eq!(lines, vec!["next"]);
+        assert_eq!(dropped, 0);
+        assert!(!state.skipping_oversized);
+    }
+
+    #[test]
+    fn feed_drops_line_growing_past_the_limit_across_reads() {
+        let mut state = FileReadState::at(0);
+
+        let (lines, dropped) = state.feed(&vec![b'x'; MAX_LINE_BYTES - 10]);
+        assert!(lines.is_empty());
+        assert_eq!(dropped, 0);
+        assert_eq!(state.pending.len(), MAX_LINE_BYTES - 10);
+
+        let mut bytes = vec![b'x'; 20];
+        bytes.extend_from_slice(b"\nok\n");
+        let (lines, dropped) = state.feed(&bytes);
+        assert_eq!(lines, vec!["ok"]);
+        assert_eq!(dropped, 1);
+        assert!(state.pending.is_empty());
+    }
+}
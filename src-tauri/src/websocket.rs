@@ -1,42 +1,102 @@
 use futures_util::{SinkExt, StreamExt};
+use std::io;
 use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::broadcast;
+use tokio::time::Instant;
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::http;
 use tokio_tungstenite::tungstenite::Message;
 
-use crate::state::SharedState;
+use crate::auth::{AuthPolicy, Scope};
+use crate::commands::ServerContext;
+use crate::config::ServerConfig;
+use crate::pipeline::{Sink, StateUpdate};
+use crate::protocol::{self, Command, ServerMessage, Subscription};
+use crate::rest;
+use crate::runtime::{self, ServerInfo};
+use crate::state::ClaudyState;
+use crate::watcher::ClaudeEvent;
 
-const WS_PORT: u16 = 3695;
+/// How many ports after the configured one are tried with `port_fallback = "next"`
+const PORT_FALLBACK_ATTEMPTS: u16 = 10;
 
-pub type StateBroadcaster = broadcast::Sender<String>;
+/// Clients are pinged this often...
+const PING_INTERVAL: Duration = Duration::from_secs(20);
 
-/// Start the WebSocket server and return a broadcaster for sending state updates
-pub async fn start_server(shared_state: SharedState) -> Option<StateBroadcaster> {
-    let addr = format!("0.0.0.0:{}", WS_PORT);
+/// ...and dropped when nothing, not even a pong, arrived for this long
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(60);
 
-    let listener = match TcpListener::bind(&addr).await {
-        Ok(l) => l,
-        Err(e) => {
-            eprintln!("[Claudy WS] Failed to bind to {}: {}", addr, e);
-            return None;
-        }
-    };
+/// Something every connected client may receive, depending on its subscription
+#[derive(Debug, Clone)]
+pub enum Outgoing {
+    State(Arc<ClaudyState>),
+    Event(Arc<ClaudeEvent>),
+}
 
-    eprintln!("[Claudy WS] Server listening on ws://0.0.0.0:{}", WS_PORT);
+pub type StateBroadcaster = broadcast::Sender<Outgoing>;
 
-    // Broadcast channel for state updates (capacity 16 should be plenty)
-    let (tx, _rx) = broadcast::channel::<String>(16);
-    let tx_clone = tx.clone();
+/// Create the broadcaster shared by the server and the pipeline sink,
+/// buffering `capacity` messages per client
+pub fn new_broadcaster(capacity: usize) -> StateBroadcaster {
+    broadcast::channel::<Outgoing>(capacity.max(1)).0
+}
+
+/// Pipeline sink forwarding every state update to connected clients
+pub struct BroadcastSink(StateBroadcaster);
+
+impl BroadcastSink {
+    pub fn new(tx: StateBroadcaster) -> Self {
+        Self(tx)
+    }
+}
+
+impl Sink for BroadcastSink {
+    fn handle(&self, update: &StateUpdate) {
+        // Ignore send errors (no receivers is fine)
+        let _ = self.0.send(Outgoing::Event(Arc::new(update.event.clone())));
+        broadcast_state(&self.0, &update.state);
+    }
+}
+
+/// Start the WebSocket server, serving updates sent through the context's
+/// broadcaster. The bound address is written to the runtime dir for the CLI
+/// and other local tools.
+pub async fn start_server(ctx: ServerContext, config: &ServerConfig) -> io::Result<ServerInfo> {
+    let tokens = runtime::load_or_create_tokens()?;
+    let policy = Arc::new(AuthPolicy::new(config, tokens));
+
+    let listener = bind_listener(config).await?;
+    let local_addr = listener.local_addr()?;
+
+    let info = ServerInfo {
+        host: config.bind.clone(),
+        port: local_addr.port(),
+        pid: std::process::id(),
+    };
+    if let Err(e) = runtime::write_server_info(&info) {
+        eprintln!("[Claudy WS] Failed to write server info: {}", e);
+    }
+
+    eprintln!("[Claudy WS] Server listening on ws://{}", local_addr);
 
     // Spawn the accept loop
     tokio::spawn(async move {
         loop {
             match listener.accept().await {
                 Ok((stream, addr)) => {
-                    let rx = tx_clone.subscribe();
-                    let tx = tx_clone.clone();
-                    let state = shared_state.clone();
-                    tokio::spawn(handle_connection(stream, addr, rx, tx, state));
+                    let ctx = ctx.clone();
+                    let policy = policy.clone();
+                    tokio::spawn(async move {
+                        // Plain HTTP requests share the port
+                        if rest::is_websocket_upgrade(&stream).await {
+                            handle_connection(stream, addr, ctx, policy).await;
+                        } else {
+                            rest::handle_request(stream, addr, ctx, policy).await;
+                        }
+                    });
                 }
                 Err(e) => {
                     eprintln!("[Claudy WS] Accept error: {}", e);
@@ -45,19 +105,62 @@ pub async fn start_server(shared_state: SharedState) -> Option<StateBroadcaster>
         }
     });
 
-    Some(tx)
+    Ok(info)
+}
+
+async fn bind_listener(config: &ServerConfig) -> io::Result<TcpListener> {
+    let attempts = if config.port_fallback == "next" {
+        PORT_FALLBACK_ATTEMPTS
+    } else {
+        1
+    };
+
+    let mut last_error = None;
+    for port in (config.port..=u16::MAX).take(attempts.into()) {
+        match TcpListener::bind((config.bind.as_str(), port)).await {
+            Ok(listener) => return Ok(listener),
+            Err(e) if e.kind() == io::ErrorKind::AddrInUse => {
+                eprintln!("[Claudy WS] Port {} is taken", port);
+                last_error = Some(e);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    Err(last_error.unwrap_or_else(|| io::Error::new(io::ErrorKind::AddrInUse, "no free port")))
 }
 
 async fn handle_connection(
     stream: TcpStream,
     addr: SocketAddr,
-    mut rx: broadcast::Receiver<String>,
-    tx: StateBroadcaster,
-    shared_state: SharedState,
+    ctx: ServerContext,
+    policy: Arc<AuthPolicy>,
 ) {
     eprintln!("[Claudy WS] New connection from {}", addr);
 
-    let ws_stream = match tokio_tungstenite::accept_async(stream).await {
+    // Token and Origin are checked during the handshake
+    let mut scope = Scope::Read;
+    #[allow(clippy::result_large_err)] // Signature dictated by tungstenite
+    let authorize = |request: &Request, response: Response| -> Result<Response, ErrorResponse> {
+        let header = |name: &str| request.headers().get(name).and_then(|v| v.to_str().ok());
+        let token = crate::auth::request_token(request.uri().query(), header);
+
+        match policy.authorize(header("origin"), token) {
+            Ok(granted) => {
+                scope = granted;
+                Ok(response)
+            }
+            Err(e) => {
+                eprintln!("[Claudy WS] Rejected {}: {}", addr, e.message());
+                let mut error = ErrorResponse::new(Some(e.message().to_string()));
+                *error.status_mut() =
+                    http::StatusCode::from_u16(e.status()).unwrap_or(http::StatusCode::FORBIDDEN);
+                Err(error)
+            }
+        }
+    };
+
+    let ws_stream = match tokio_tungstenite::accept_hdr_async(stream, authorize).await {
         Ok(ws) => ws,
         Err(e) => {
             eprintln!("[Claudy WS] WebSocket handshake failed for {}: {}", addr, e);
@@ -65,70 +168,82 @@ async fn handle_connection(
         }
     };
 
+    let mut rx = ctx.broadcaster.subscribe();
     let (mut write, mut read) = ws_stream.split();
 
     // Send intro state on connect (so the intro animation plays)
-    let initial_json = {
-        let state = shared_state.lock().unwrap();
+    let mut initial = {
+        let state = ctx.state.lock().unwrap();
         let mut intro_state = state.clone();
         intro_state.current_state = "intro".to_string();
-        serde_json::to_string(&intro_state).unwrap_or_else(|_| "{}".to_string())
+        ServerMessage::state(intro_state)
     }; // MutexGuard dropped here, before await
+    let mut seq = 1;
+    initial.seq = seq;
 
-    if let Err(e) = write.send(Message::Text(initial_json)).await {
+    if let Err(e) = write.send(Message::Text(initial.to_json())).await {
         eprintln!("[Claudy WS] Failed to send initial state to {}: {}", addr, e);
         return;
     }
 
-    // Spawn a task to handle incoming messages (state updates from clients)
-    let addr_clone = addr;
-    let shared_state_clone = shared_state.clone();
-    tokio::spawn(async move {
-        while let Some(msg) = read.next().await {
-            match msg {
-                Ok(Message::Text(text)) => {
-                    // Try to parse as state update
-                    if let Ok(incoming) = serde_json::from_str::<crate::state::ClaudyState>(&text) {
-                        eprintln!("[Claudy WS] Received state from {}: {} (bubble: {:?})",
-                            addr_clone, incoming.current_state, incoming.bubble_text);
-                        // Update shared state (but not bubble_text - it's ephemeral)
-                        {
-                            let mut state = shared_state_clone.lock().unwrap();
-                            state.current_state = incoming.current_state.clone();
-                            if !incoming.active_projects.is_empty() {
-                                state.active_projects = incoming.active_projects.clone();
-                            }
-                        }
-                        // Broadcast incoming state directly (includes bubble_text)
-                        if let Ok(json) = serde_json::to_string(&incoming) {
-                            let _ = tx.send(json);
+    let mut client = ctx.clients.register(addr, scope);
+    let mut subscription = Subscription::default();
+    let mut heartbeat = tokio::time::interval(PING_INTERVAL);
+    let mut last_seen = Instant::now();
+
+    loop {
+        let reply = tokio::select! {
+            incoming = read.next() => match incoming {
+                Some(Ok(message)) => {
+                    last_seen = Instant::now();
+                    match message {
+                        Message::Text(text) => {
+                            Some(handle_text(&ctx, scope, client.id, &mut subscription, &text).await)
                         }
+                        Message::Close(_) => break,
+                        // Pings are answered by tungstenite, pongs only count as a sign of life
+                        _ => None,
                     }
                 }
-                Ok(Message::Close(_)) => break,
-                Err(_) => break,
-                _ => {}
-            }
-        }
-        eprintln!("[Claudy WS] Read loop ended for {}", addr_clone);
-    });
-
-    // Forward broadcast messages to this client
-    loop {
-        match rx.recv().await {
-            Ok(state_json) => {
-                if let Err(e) = write.send(Message::Text(state_json)).await {
-                    eprintln!("[Claudy WS] Send error for {}: {}", addr, e);
+                Some(Err(_)) | None => break,
+            },
+            outgoing = rx.recv() => match outgoing {
+                Ok(message) => render(&subscription, message),
+                Err(broadcast::error::RecvError::Closed) => {
+                    eprintln!("[Claudy WS] Broadcast channel closed");
                     break;
                 }
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    eprintln!("[Claudy WS] Client {} lagged by {} messages", addr, n);
+                    ctx.clients.update(client.id, |c| c.lagged += n);
+                    // Let the client see the gap, then bring it up to date
+                    seq += n;
+                    Some(snapshot(&ctx))
+                }
+            },
+            _ = heartbeat.tick() => {
+                if last_seen.elapsed() > CLIENT_TIMEOUT {
+                    eprintln!("[Claudy WS] Client {} timed out", addr);
+                    break;
+                }
+                if write.send(Message::Ping(Vec::new())).await.is_err() {
+                    break;
+                }
+                None
             }
-            Err(broadcast::error::RecvError::Closed) => {
-                eprintln!("[Claudy WS] Broadcast channel closed");
+            _ = &mut client.kicked => {
+                eprintln!("[Claudy WS] Kicked client {}", addr);
+                let _ = write.send(Message::Close(None)).await;
                 break;
             }
-            Err(broadcast::error::RecvError::Lagged(n)) => {
-                eprintln!("[Claudy WS] Client {} lagged by {} messages", addr, n);
-                // Continue anyway, they'll get the next update
+        };
+
+        if let Some(mut message) = reply {
+            seq += 1;
+            message.seq = seq;
+            if let Err(e) = write.send(Message::Text(message.to_json())).await {
+                eprintln!("[Claudy WS] Send error for {}: {}", addr, e);
+                break;
             }
         }
     }
@@ -136,15 +251,70 @@ async fn handle_connection(
     eprintln!("[Claudy WS] Connection closed for {}", addr);
 }
 
-/// Broadcast state to all connected clients
-pub fn broadcast_state(tx: &StateBroadcaster, state: &crate::state::ClaudyState) {
-    match serde_json::to_string(state) {
-        Ok(json) => {
-            // Ignore send errors (no receivers is fine)
-            let _ = tx.send(json);
+/// Answer a client message with a `response` or `error`
+pub(crate) async fn handle_text(
+    ctx: &ServerContext,
+    scope: Scope,
+    client_id: u64,
+    subscription: &mut Subscription,
+    text: &str,
+) -> ServerMessage {
+    let (id, command) = match protocol::parse_client_message(text) {
+        Ok(parsed) => parsed,
+        Err((id, error)) => return ServerMessage::error(id, error),
+    };
+
+    let result = match command {
+        Command::Subscribe(requested) => {
+            *subscription = requested;
+            ctx.clients
+                .update(client_id, |c| c.subscription = subscription.clone());
+            serde_json::to_value(&*subscription).map_err(|e| {
+                protocol::ProtocolError::new(protocol::ErrorCode::Internal, e.to_string())
+            })
         }
-        Err(e) => {
-            eprintln!("[Claudy WS] Failed to serialize state: {}", e);
+        Command::Resync => {
+            let mut message = snapshot(ctx);
+            message.id = id;
+            return message;
+        }
+        Command::Hello { name, role } => {
+            ctx.clients.update(client_id, |c| {
+                c.name = name;
+                c.role = role;
+            });
+            Ok(serde_json::json!({ "id": client_id }))
+        }
+        command => ctx.execute(command, scope).await,
+    };
+
+    match result {
+        Ok(value) => ServerMessage::response(id, value),
+        Err(error) => ServerMessage::error(id, error),
+    }
+}
+
+/// Current state, for clients that need to catch up
+pub(crate) fn snapshot(ctx: &ServerContext) -> ServerMessage {
+    let state = ctx.state.lock().unwrap().clone();
+    ServerMessage::state(state)
+}
+
+/// Turn a broadcast into a message for this client, if it is subscribed
+pub(crate) fn render(subscription: &Subscription, outgoing: Outgoing) -> Option<ServerMessage> {
+    match outgoing {
+        Outgoing::State(state) if subscription.state => {
+            Some(ServerMessage::state(Arc::unwrap_or_clone(state)))
         }
+        Outgoing::Event(event) if subscription.wants_event(&event) => {
+            Some(ServerMessage::event(Arc::unwrap_or_clone(event)))
+        }
+        _ => None,
     }
 }
+
+/// Broadcast state to all connected clients
+pub fn broadcast_state(tx: &StateBroadcaster, state: &ClaudyState) {
+    // Ignore send errors (no receivers is fine)
+    let _ = tx.send(Outgoing::State(Arc::new(state.clone())));
+}
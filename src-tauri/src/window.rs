@@ -1,36 +1,360 @@
-use tauri::{LogicalPosition, WebviewWindow};
-use crate::config::Config;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tauri::{LogicalSize, Monitor, PhysicalPosition, PhysicalRect, PhysicalSize, WebviewWindow, WindowEvent};
+use tokio::sync::watch;
 
+use crate::config::{self, Config, PositionConfig, WindowConfig};
+use crate::focus;
+
+/// How often monitors, and the cursor or focused window when followed, are
+/// checked
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Window size at `appearance.size = "medium"`, as in tauri.conf.json
+const BASE_WIDTH: f64 = 200.0;
+const BASE_HEIGHT: f64 = 250.0;
+
+/// How long the window must stay put before a drag counts as finished
+const MOVE_SETTLE: Duration = Duration::from_millis(500);
+
+/// Set by the frontend as it starts dragging, to tell the user's moves from
+/// ours
+static DRAGGING: AtomicBool = AtomicBool::new(false);
+
+/// Which monitor Claudy sits on, parsed from `position.monitor`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MonitorTarget {
+    Primary,
+    /// Position in the platform's monitor list, from 0
+    Index(usize),
+    Name(String),
+    /// The monitor under the mouse pointer
+    FollowCursor,
+    /// The monitor of the focused window
+    FollowFocused,
+}
+
+impl MonitorTarget {
+    pub fn parse(value: &str) -> Self {
+        match value.trim() {
+            "" | "primary" => MonitorTarget::Primary,
+            "cursor" => MonitorTarget::FollowCursor,
+            "focused" => MonitorTarget::FollowFocused,
+            other => other
+                .parse()
+                .map(MonitorTarget::Index)
+                .unwrap_or_else(|_| MonitorTarget::Name(other.to_string())),
+        }
+    }
+}
+
+/// Size the window per `appearance.size` and place it per `[position]`
 pub fn position_window(window: &WebviewWindow, config: &Config) {
-    let monitor = window.current_monitor().ok().flatten();
-
-    if let Some(monitor) = monitor {
-        let size = monitor.size();
-        let scale = monitor.scale_factor();
-
-        let window_size = window.outer_size().unwrap_or_default();
-
-        let (x, y) = match config.position.anchor.as_str() {
-            "bottom-right" => (
-                (size.width as f64 / scale) as i32 - window_size.width as i32 - config.position.x,
-                (size.height as f64 / scale) as i32 - window_size.height as i32 - config.position.y,
-            ),
-            "bottom-left" => (
-                config.position.x,
-                (size.height as f64 / scale) as i32 - window_size.height as i32 - config.position.y,
-            ),
-            "top-right" => (
-                (size.width as f64 / scale) as i32 - window_size.width as i32 - config.position.x,
-                config.position.y,
-            ),
-            "top-left" => (
-                config.position.x,
-                config.position.y,
-            ),
-            _ => (config.position.x, config.position.y),
+    let target = MonitorTarget::parse(&config.position.monitor);
+    if let Some(monitor) = target_monitor(window, &target) {
+        place(window, &monitor, config);
+    }
+}
+
+/// Apply the `[window]` toggles
+pub fn apply_modes(window: &WebviewWindow, modes: &WindowConfig) {
+    let _ = window.set_always_on_top(modes.always_on_top);
+    let _ = window.set_ignore_cursor_events(modes.click_through);
+    let _ = window.set_skip_taskbar(modes.skip_taskbar);
+    let _ = window.set_visible_on_all_workspaces(modes.all_workspaces);
+}
+
+/// Change `appearance.size` and apply it, keeping the anchored corner in place
+pub fn set_size(window: &WebviewWindow, size: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if config::parse_size(size).is_none() {
+        return Err(format!("invalid size {:?}", size).into());
+    }
+    let mut cfg = config::load_config();
+    cfg.appearance.size = size.to_string();
+    config::save_config(&cfg)?;
+    position_window(window, &cfg);
+    Ok(())
+}
+
+/// Go back to the default corner, on the same monitor
+pub fn reset_position(window: &WebviewWindow) -> Result<(), Box<dyn std::error::Error>> {
+    let mut cfg = config::load_config();
+    let default = PositionConfig::default();
+    cfg.position.anchor = default.anchor;
+    cfg.position.x = default.x;
+    cfg.position.y = default.y;
+    config::save_config(&cfg)?;
+    position_window(window, &cfg);
+    Ok(())
+}
+
+/// Note that the user is about to drag the window, so that `track_drags`
+/// saves where it's dropped
+pub fn begin_drag() {
+    DRAGGING.store(true, Ordering::Relaxed);
+}
+
+/// Save where the user drops the window, as offsets from the nearest corner
+/// of the monitor it ended up on
+pub fn track_drags(window: &WebviewWindow) {
+    let (tx, rx) = mpsc::channel();
+    window.on_window_event(move |event| {
+        if let WindowEvent::Moved(position) = event {
+            let _ = tx.send(*position);
+        }
+    });
+
+    let window = window.clone();
+    thread::spawn(move || {
+        while let Ok(mut position) = rx.recv() {
+            while let Ok(next) = rx.recv_timeout(MOVE_SETTLE) {
+                position = next;
+            }
+            if !DRAGGING.swap(false, Ordering::Relaxed) {
+                continue;
+            }
+            if let Err(e) = save_position(&window, position) {
+                eprintln!("[Claudy] Failed to save the window position: {}", e);
+            }
+        }
+    });
+}
+
+fn save_position(
+    window: &WebviewWindow,
+    position: PhysicalPosition<i32>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let size = window.outer_size()?;
+    let center_x = position.x as f64 + size.width as f64 / 2.0;
+    let center_y = position.y as f64 + size.height as f64 / 2.0;
+    let Some(monitor) = window.monitor_from_point(center_x, center_y)? else {
+        return Ok(());
+    };
+
+    let mut cfg = config::load_config();
+    let area = monitor.work_area();
+    let scale = monitor.scale_factor();
+    let (x, y) = clamp(area, position.x, position.y, size);
+    let (left, top, right, bottom) = edges(area);
+
+    let from_left = x - left;
+    let from_right = right - (x + size.width as i32);
+    let from_top = y - top;
+    let from_bottom = bottom - (y + size.height as i32);
+    let (horizontal, dx) = if from_left <= from_right {
+        ("left", from_left)
+    } else {
+        ("right", from_right)
+    };
+    let (vertical, dy) = if from_top <= from_bottom {
+        ("top", from_top)
+    } else {
+        ("bottom", from_bottom)
+    };
+
+    cfg.position.anchor = format!("{}-{}", vertical, horizontal);
+    cfg.position.x = (dx as f64 / scale).round() as i32;
+    cfg.position.y = (dy as f64 / scale).round() as i32;
+
+    // Dropped on another monitor than the configured one
+    let target = MonitorTarget::parse(&cfg.position.monitor);
+    if !matches!(target, MonitorTarget::FollowCursor | MonitorTarget::FollowFocused)
+        && target_monitor(window, &target).map(|m| monitor_key(&m)) != Some(monitor_key(&monitor))
+    {
+        let monitors = window.available_monitors()?;
+        cfg.position.monitor = match monitor.name() {
+            Some(name) => name.clone(),
+            None => monitors
+                .iter()
+                .position(|m| monitor_key(m) == monitor_key(&monitor))
+                .unwrap_or_default()
+                .to_string(),
         };
+    }
+
+    config::save_config(&cfg)?;
+    println!(
+        "Window dropped at ({}, {}) from {} on {}",
+        cfg.position.x, cfg.position.y, cfg.position.anchor, cfg.position.monitor
+    );
+    Ok(())
+}
+
+/// Keep the window placed while monitors are plugged in or unplugged or
+/// change resolution or scale, and when the config is reloaded, and move it
+/// along when following the cursor or the focused window. A dragged window
+/// stays put until one of those changes.
+pub fn spawn_positioner(window: WebviewWindow, mut config: watch::Receiver<Arc<Config>>) {
+    let (tx, rx) = mpsc::channel();
+    let events = tx.clone();
+    window.on_window_event(move |event| {
+        if matches!(event, WindowEvent::ScaleFactorChanged { .. } | WindowEvent::Resized(_)) {
+            let _ = events.send(());
+        }
+    });
+
+    let latest = config.clone();
+    tauri::async_runtime::spawn(async move {
+        while config.changed().await.is_ok() {
+            if tx.send(()).is_err() {
+                break;
+            }
+        }
+    });
+
+    thread::spawn(move || {
+        let mut last = None;
+        loop {
+            // Monitors coming and going, the cursor and the focused window
+            // have no event to wait for
+            if let Err(RecvTimeoutError::Disconnected) = rx.recv_timeout(POLL_INTERVAL) {
+                break;
+            }
+
+            let cfg = latest.borrow().clone();
+            let target = MonitorTarget::parse(&cfg.position.monitor);
+            let Some(monitor) = target_monitor(&window, &target) else {
+                continue;
+            };
+            let layout = window.available_monitors().unwrap_or_default();
+
+            // Our own resizing wakes this up too, and changes nothing here
+            let key = (
+                layout.iter().map(monitor_key).collect::<Vec<_>>(),
+                monitor_key(&monitor),
+                format!("{:?}", cfg.position),
+                cfg.appearance.size.clone(),
+            );
+            if last.as_ref() != Some(&key) {
+                place(&window, &monitor, &cfg);
+                last = Some(key);
+            }
+        }
+    });
+}
+
+fn target_monitor(window: &WebviewWindow, target: &MonitorTarget) -> Option<Monitor> {
+    let found = match target {
+        MonitorTarget::Primary => None,
+        MonitorTarget::Index(index) => window
+            .available_monitors()
+            .ok()
+            .and_then(|monitors| monitors.into_iter().nth(*index)),
+        MonitorTarget::Name(name) => window
+            .available_monitors()
+            .ok()
+            .and_then(|monitors| monitors.into_iter().find(|m| m.name() == Some(name))),
+        MonitorTarget::FollowCursor => window
+            .cursor_position()
+            .ok()
+            .and_then(|p| window.monitor_from_point(p.x, p.y).ok().flatten()),
+        MonitorTarget::FollowFocused => focus::active_window_center()
+            .and_then(|(x, y)| window.monitor_from_point(x, y).ok().flatten()),
+    };
+
+    // Unplugged or unknown monitors fall back to the primary one
+    found
+        .or_else(|| window.primary_monitor().ok().flatten())
+        .or_else(|| window.current_monitor().ok().flatten())
+}
+
+/// Resize the window and anchor it inside the monitor's work area, never past
+/// its edges. Positions are computed in physical pixels at the monitor's
+/// scale, since that is the scale the window takes once it's there, and from
+/// the new size so the anchored corner doesn't move.
+fn place(window: &WebviewWindow, monitor: &Monitor, config: &Config) {
+    let position = &config.position;
+    let size = config.appearance.scale();
+    let _ = window.set_size(LogicalSize::new(BASE_WIDTH * size, BASE_HEIGHT * size));
+    // Scale the content along with the window
+    let _ = window.set_zoom(size);
+
+    let area = monitor.work_area();
+    let scale = monitor.scale_factor();
+    let width = (BASE_WIDTH * size * scale) as i32;
+    let height = (BASE_HEIGHT * size * scale) as i32;
+    let dx = (position.x as f64 * scale) as i32;
+    let dy = (position.y as f64 * scale) as i32;
+
+    let (left, top, right, bottom) = edges(area);
+
+    let (x, y) = match position.anchor.as_str() {
+        "bottom-right" => (right - width - dx, bottom - height - dy),
+        "bottom-left" => (left + dx, bottom - height - dy),
+        "top-right" => (right - width - dx, top + dy),
+        _ => (left + dx, top + dy),
+    };
+    let (x, y) = clamp(area, x, y, PhysicalSize::new(width as u32, height as u32));
+
+    let _ = window.set_position(PhysicalPosition::new(x, y));
+    println!(
+        "Window positioned at ({}, {}) on {} with anchor {}",
+        x,
+        y,
+        monitor.name().map_or("unnamed monitor", |n| n.as_str()),
+        position.anchor
+    );
+}
+
+/// Left, top, right and bottom edges of a work area
+fn edges(area: &PhysicalRect<i32, u32>) -> (i32, i32, i32, i32) {
+    let left = area.position.x;
+    let top = area.position.y;
+    (left, top, left + area.size.width as i32, top + area.size.height as i32)
+}
+
+/// Move a window of the given size inside the area, as far as it fits
+fn clamp(area: &PhysicalRect<i32, u32>, x: i32, y: i32, size: PhysicalSize<u32>) -> (i32, i32) {
+    let (left, top, right, bottom) = edges(area);
+    (
+        x.clamp(left, (right - size.width as i32).max(left)),
+        y.clamp(top, (bottom - size.height as i32).max(top)),
+    )
+}
+
+/// What identifies a monitor's placement, for noticing changes
+fn monitor_key(monitor: &Monitor) -> (Option<String>, i32, i32, u32, u32, u64) {
+    let position = monitor.position();
+    let size = monitor.size();
+    (
+        monitor.name().cloned(),
+        position.x,
+        position.y,
+        size.width,
+        size.height,
+        monitor.scale_factor().to_bits(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 1280x720 work area right of a 1920 pixel wide monitor
+    fn area() -> PhysicalRect<i32, u32> {
+        PhysicalRect {
+            position: PhysicalPosition::new(1920, 0),
+            size: PhysicalSize::new(1280, 720),
+        }
+    }
+
+    #[test]
+    fn clamp_keeps_the_window_inside_a_smaller_area() {
+        let size = PhysicalSize::new(200, 250);
+        // Saved for a larger monitor, past the bottom right corner
+        assert_eq!(clamp(&area(), 3100, 900, size), (3000, 470));
+        // On the monitor to the left, above the top
+        assert_eq!(clamp(&area(), 100, -50, size), (1920, 0));
+        // Already inside
+        assert_eq!(clamp(&area(), 2000, 100, size), (2000, 100));
+    }
 
-        let _ = window.set_position(LogicalPosition::new(x, y));
-        println!("Window positioned at ({}, {}) with anchor {}", x, y, config.position.anchor);
+    #[test]
+    fn clamp_pins_an_oversized_window_to_the_top_left() {
+        let size = PhysicalSize::new(2000, 1000);
+        assert_eq!(clamp(&area(), 2500, 300, size), (1920, 0));
     }
 }
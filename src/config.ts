@@ -11,12 +11,25 @@ interface Config {
   projects: {
     registered: string[];
   };
+  behavior: {
+    auto_start: boolean;
+  };
+}
+
+// Where the app's server listens, from its runtime file
+interface ServerInfo {
+  host: string;
+  port: number;
+  pid: number;
 }
 
 // Elements
 const backgroundInput = document.getElementById("background") as HTMLInputElement;
 const backgroundPicker = document.getElementById("background-picker") as HTMLInputElement;
 const themeSelect = document.getElementById("theme") as HTMLSelectElement;
+const sizeSelect = document.getElementById("size") as HTMLSelectElement;
+const autoStartInput = document.getElementById("auto-start") as HTMLInputElement;
+const serverInfoEl = document.getElementById("server-info") as HTMLDivElement;
 const projectList = document.getElementById("project-list") as HTMLUListElement;
 const saveBtn = document.getElementById("save-btn") as HTMLButtonElement;
 const closeBtn = document.getElementById("close-btn") as HTMLButtonElement;
@@ -84,6 +97,20 @@ async function removeProject(path: string) {
   }
 }
 
+async function loadServerInfo() {
+  try {
+    const info = await invoke<ServerInfo | null>("get_server_info");
+    if (!info) {
+      serverInfoEl.textContent = "Not running; overlays can't connect";
+      return;
+    }
+    const host = info.host.includes(":") ? `[${info.host}]` : info.host;
+    serverInfoEl.textContent = `Listening on ws://${host}:${info.port}`;
+  } catch (e) {
+    serverInfoEl.textContent = `Failed to get server info: ${e}`;
+  }
+}
+
 async function loadConfig() {
   try {
     currentConfig = await invoke<Config>("get_full_config");
@@ -100,6 +127,16 @@ async function loadConfig() {
       themeSelect.value = currentConfig.appearance.theme;
     }
 
+    // Custom scale factors from the config file aren't in the list
+    const size = currentConfig.appearance.size;
+    if (!Array.from(sizeSelect.options).some((o) => o.value === size)) {
+      sizeSelect.add(new Option(`Custom (${size})`, size));
+    }
+    sizeSelect.value = size;
+
+    // Set behavior values
+    autoStartInput.checked = currentConfig.behavior.auto_start;
+
     // Render projects
     renderProjects(currentConfig.projects.registered);
   } catch (e) {
@@ -112,6 +149,7 @@ async function saveConfig() {
     const background = backgroundInput.value.trim() || null;
     const theme = themeSelect.value;
     await invoke("save_appearance_config", { background, theme });
+    await invoke("set_size", { size: sizeSelect.value });
 
     // Notify main window to apply theme immediately
     await emitTo("main", "theme-changed", { theme, background });
@@ -122,6 +160,19 @@ async function saveConfig() {
   }
 }
 
+// Applied right away, since it installs or removes the autostart entry
+autoStartInput.addEventListener("change", async () => {
+  try {
+    autoStartInput.checked = await invoke<boolean>("set_auto_start", {
+      enabled: autoStartInput.checked,
+    });
+    showStatus(autoStartInput.checked ? "Claudy will start on login" : "Autostart disabled");
+  } catch (e) {
+    autoStartInput.checked = !autoStartInput.checked;
+    showStatus(`Failed to change autostart: ${e}`, true);
+  }
+});
+
 // Event listeners
 saveBtn.addEventListener("click", saveConfig);
 
@@ -132,3 +183,4 @@ closeBtn.addEventListener("click", async () => {
 
 // Load config on startup
 loadConfig();
+loadServerInfo();
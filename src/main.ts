@@ -9,14 +9,25 @@ import "./claudy.css";
  * Backend event type (matches Rust ClaudeEvent enum)
  */
 interface BackendEvent {
-  SessionStart?: { project: string };
-  UserMessage?: { project: string };
-  Thinking?: { project: string };
-  ToolUse?: { project: string; tool: string; file_path?: string };
-  Talking?: { project: string };
-  WaitingForTask?: { project: string };
-  Stop?: { project: string; success: boolean };
-  Error?: { project: string; message: string };
+  SessionStart?: { project: string; session: string };
+  UserMessage?: { project: string; session: string };
+  Thinking?: { project: string; session: string };
+  ToolUse?: { project: string; session: string; tool: string; file_path?: string };
+  Talking?: { project: string; session: string };
+  WaitingForTask?: { project: string; session: string };
+  Stop?: { project: string; session: string; success: boolean };
+  Error?: { project: string; session: string; message: string };
+  NeedsAttention?: { project: string; session: string };
+}
+
+/**
+ * Speech bubble shown by the backend queue (matches Rust speech::Bubble)
+ */
+interface Bubble {
+  text: string;
+  duration: number;
+  priority: "low" | "normal" | "high";
+  mood?: string;
 }
 
 /**
@@ -27,10 +38,17 @@ interface BackendState {
   active_projects: string[];
   focused_project?: string;
   last_event?: BackendEvent;
-  bubble_text?: string;
+  bubble?: Bubble;
   suppress_comments?: boolean;
 }
 
+// WebSocket protocol v1 envelope (see `claudy-cli schema server`)
+type ServerMessage =
+  | { v: number; type: "state"; state: BackendState }
+  | { v: number; type: "event"; event: BackendEvent }
+  | { v: number; id?: unknown; type: "response"; result: unknown }
+  | { v: number; id?: unknown; type: "error"; code: string; message: string };
+
 // Detect if running in Tauri or browser (Tauri 2 uses __TAURI_INTERNALS__)
 const isTauri = '__TAURI__' in window || '__TAURI_INTERNALS__' in window;
 console.log("[Claudy] Environment check - isTauri:", isTauri,
@@ -207,7 +225,7 @@ function backendEventToRawEvent(event: BackendEvent): RawClaudeEvent {
       error: event.Error.message,
     };
   }
-  if (event.WaitingForTask) {
+  if (event.WaitingForTask || event.NeedsAttention) {
     return { type: "waiting", timestamp };
   }
 
@@ -237,10 +255,33 @@ function stateToRawEvent(state: ClaudyState): RawClaudeEvent {
 // Flag to suppress personality comments when custom bubble is active
 let suppressPersonalityComments = false;
 
+// Quiet hours or Do-Not-Disturb: no bubbles, and optionally no animation
+interface QuietStatus {
+  quiet: boolean;
+  dnd: boolean;
+  dnd_until?: number;
+  freeze_animations: boolean;
+}
+let quiet = false;
+
+function applyQuietStatus(status: QuietStatus) {
+  console.log("[Claudy] Quiet status:", status);
+  quiet = status.quiet;
+  document.body.classList.toggle("frozen", status.quiet && status.freeze_animations);
+  if (quiet) {
+    bubble.classList.add("hidden");
+  }
+}
+
 // Subscribe to context updates for personality-based comments
 contextState.subscribe((ctx) => {
   console.log("[Claudy Engine] Context update:", ctx.event);
 
+  if (quiet) {
+    console.log("[Claudy Engine] Skipping personality comment (quiet)");
+    return;
+  }
+
   // Skip if custom bubble is active
   if (suppressPersonalityComments) {
     console.log("[Claudy Engine] Skipping personality comment (custom bubble active)");
@@ -255,9 +296,24 @@ contextState.subscribe((ctx) => {
   }
 });
 
+// Show a backend bubble; repeated snapshots of the same bubble are ignored
+let currentBubble: Bubble | null = null;
+function handleBubble(bubble: Bubble | null) {
+  if (bubble && currentBubble && bubble.text === currentBubble.text) return;
+  currentBubble = bubble;
+  if (!bubble || quiet) return;
+
+  suppressPersonalityComments = true;
+  showBubble(bubble.text, bubble.duration * 1000);
+  // Re-enable personality comments after bubble duration
+  setTimeout(() => {
+    suppressPersonalityComments = false;
+  }, bubble.duration * 1000);
+}
+
 // Handle state update (shared between Tauri and WebSocket)
-function handleStateUpdate(state: ClaudyState, projects?: string[], lastEvent?: BackendEvent, bubbleText?: string, suppressComments?: boolean) {
-  console.log("[Claudy Frontend] Received state:", state, "event:", lastEvent, "bubble:", bubbleText, "suppress:", suppressComments);
+function handleStateUpdate(state: ClaudyState, projects?: string[], lastEvent?: BackendEvent, bubble?: Bubble, suppressComments?: boolean) {
+  console.log("[Claudy Frontend] Received state:", state, "event:", lastEvent, "bubble:", bubble, "suppress:", suppressComments);
 
   // Update CSS animation (direct, for responsiveness)
   claudy.setState(state);
@@ -276,15 +332,7 @@ function handleStateUpdate(state: ClaudyState, projects?: string[], lastEvent?:
     }, 5000);
   }
 
-  // Show bubble text if provided (for demos/external control)
-  if (bubbleText) {
-    suppressPersonalityComments = true;
-    showBubble(bubbleText);
-    // Re-enable personality comments after bubble duration
-    setTimeout(() => {
-      suppressPersonalityComments = false;
-    }, 5000);
-  }
+  handleBubble(bubble ?? null);
 
   // Update projects if provided (WebSocket sends full state)
   if (projects) {
@@ -304,12 +352,53 @@ function handleStateUpdate(state: ClaudyState, projects?: string[], lastEvent?:
   contextState.handleEvent(rawEvent);
 }
 
-// WebSocket connection for browser mode
-const WS_PORT = 3695;
+// WebSocket connection for browser mode (override with ?host=...&port=...&token=...)
+const urlParams = new URLSearchParams(window.location.search);
+const WS_DEFAULT_PORT = 3695;
+const WS_TOKEN = urlParams.get('token');
 const WS_RECONNECT_DELAY = 5000;
 
-function connectWebSocket() {
-  const wsUrl = `ws://${window.location.hostname}:${WS_PORT}`;
+// Where the app's server listens, as written to its runtime file
+interface ServerInfo {
+  host: string;
+  port: number;
+}
+
+// Address local clients connect to, like ServerInfo::connect_host
+function connectHost(host: string): string {
+  if (host === "0.0.0.0") return "127.0.0.1";
+  if (host === "::") return "::1";
+  return host;
+}
+
+// URL params win; otherwise use the runtime file the dev server exposes, which
+// has the port the app fell back to when the configured one was taken
+async function resolveServerAddress(): Promise<string> {
+  let host = urlParams.get('host');
+  let port = Number(urlParams.get('port')) || null;
+
+  if (!host || !port) {
+    try {
+      const response = await fetch("./server-info.json");
+      if (response.ok) {
+        const info = await response.json() as ServerInfo;
+        host = host || connectHost(info.host);
+        port = port || info.port;
+      }
+    } catch (e) {
+      console.log("[Claudy WS] No server info, using defaults:", e);
+    }
+  }
+
+  host = host || window.location.hostname;
+  const bracketed = host.includes(":") && !host.startsWith("[") ? `[${host}]` : host;
+  return `${bracketed}:${port || WS_DEFAULT_PORT}`;
+}
+
+async function connectWebSocket() {
+  const tokenQuery = WS_TOKEN ? `?token=${encodeURIComponent(WS_TOKEN)}` : "";
+  // Resolved on every attempt, since the app may restart on another port
+  const wsUrl = `ws://${await resolveServerAddress()}${tokenQuery}`;
   console.log("[Claudy WS] Connecting to", wsUrl);
 
   stateLabel.textContent = "connecting...";
@@ -322,13 +411,15 @@ function connectWebSocket() {
 
   ws.onmessage = (event) => {
     try {
-      const data = JSON.parse(event.data) as BackendState;
+      const message = JSON.parse(event.data) as ServerMessage;
+      if (message.type !== "state") return;
+      const data = message.state;
       const state = data.current_state as ClaudyState;
       const projects = data.active_projects;
       const lastEvent = data.last_event;
-      const bubbleText = data.bubble_text;
+      const bubble = data.bubble;
       const suppressComments = data.suppress_comments;
-      handleStateUpdate(state, projects, lastEvent, bubbleText, suppressComments);
+      handleStateUpdate(state, projects, lastEvent, bubble, suppressComments);
     } catch (e) {
       console.error("[Claudy WS] Failed to parse message:", e);
     }
@@ -364,6 +455,16 @@ if (isTauri) {
       });
     });
 
+    // Speech bubbles from the backend queue
+    listen<Bubble | null>("claudy-bubble", (event) => {
+      handleBubble(event.payload);
+    });
+
+    // Quiet hours and Do-Not-Disturb
+    listen<QuietStatus>("claudy-quiet", (event) => {
+      applyQuietStatus(event.payload);
+    });
+
     // Listen for theme changes from config window
     listen<{ theme: string; background?: string }>("theme-changed", (event) => {
       console.log("[Claudy] Theme changed:", event.payload);
@@ -384,6 +485,19 @@ if (isTauri) {
     });
   });
 
+  // Drag Claudy around; the backend remembers where it was dropped
+  Promise.all([import("@tauri-apps/api/window"), import("@tauri-apps/api/core")]).then(
+    ([{ getCurrentWindow }, { invoke }]) => {
+      animationContainer.addEventListener("mousedown", async (e) => {
+        if (e.button === 0) {
+          // Only moves after this are saved as the user's
+          await invoke("begin_drag");
+          getCurrentWindow().startDragging();
+        }
+      });
+    }
+  );
+
   // Initial project load
   import("@tauri-apps/api/core").then(async ({ invoke }) => {
     try {
@@ -393,6 +507,12 @@ if (isTauri) {
       console.error("Failed to get projects:", e);
     }
 
+    try {
+      applyQuietStatus(await invoke<QuietStatus>("get_quiet_status"));
+    } catch (e) {
+      console.error("Failed to get quiet status:", e);
+    }
+
     // Apply appearance config (background color and theme)
     try {
       const appearance = await invoke<{ background?: string; theme?: string }>("get_appearance_config");
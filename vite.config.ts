@@ -1,8 +1,38 @@
-import { defineConfig } from "vite";
-import { resolve } from "path";
+import { defineConfig, type Plugin, type PreviewServer, type ViteDevServer } from "vite";
+import { readFile } from "fs/promises";
+import { tmpdir } from "os";
+import { join, resolve } from "path";
+
+// Same location as runtime::runtime_dir in the backend
+function runtimeDir(): string {
+  if (process.env.XDG_RUNTIME_DIR) {
+    return join(process.env.XDG_RUNTIME_DIR, "claudy");
+  }
+  const user = process.env.USER || process.env.USERNAME || "default";
+  return join(tmpdir(), `claudy-${user}`);
+}
+
+// Serve the running app's server.json, so browser mode finds the port it
+// ended up on
+function serverInfo(): Plugin {
+  const serve = (server: ViteDevServer | PreviewServer) => {
+    server.middlewares.use("/server-info.json", async (_req, res) => {
+      try {
+        const info = await readFile(join(runtimeDir(), "server.json"));
+        res.setHeader("Content-Type", "application/json");
+        res.end(info);
+      } catch {
+        res.statusCode = 404;
+        res.end();
+      }
+    });
+  };
+  return { name: "claudy-server-info", configureServer: serve, configurePreviewServer: serve };
+}
 
 export default defineConfig({
   clearScreen: false,
+  plugins: [serverInfo()],
   root: "src",
   base: "./", // Relative paths for Tauri compatibility
   server: {